@@ -0,0 +1,103 @@
+use crate::bfs::Visit;
+use std::ops::Index;
+
+// Nodes in the order the searches reach them, each with the index of the node it was reached from
+// and the edge between them. The depth of a node is one more than its parent's, 0 for the roots.
+#[derive(Debug, Clone)]
+pub(crate) struct Arena<Node, Edge> {
+    visits: Vec<Visit<Node, Edge>>,
+}
+
+impl<Node, Edge> Arena<Node, Edge> {
+    pub(crate) fn new() -> Self {
+        Arena { visits: Vec::new() }
+    }
+
+    // Returns the index of the node, a node reached several times gets several indices
+    pub(crate) fn push(&mut self, node: Node, parent: Option<(Edge, usize)>) -> usize {
+        let depth = parent.as_ref().map_or(0, |(_, index)| self.visits[*index].depth + 1);
+        self.visits.push(Visit { node, parent, depth });
+        self.visits.len() - 1
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.visits.len()
+    }
+
+    pub(crate) fn visits(&self) -> &[Visit<Node, Edge>] {
+        &self.visits
+    }
+
+    pub(crate) fn into_visits(self) -> Vec<Visit<Node, Edge>> {
+        self.visits
+    }
+
+    // Indices from the root the node was reached from to the node
+    fn chain(&self, index: usize) -> Vec<usize> {
+        let mut chain = vec![index];
+        while let Some((_, parent)) = &self.visits[chain[chain.len() - 1]].parent {
+            chain.push(*parent);
+        }
+        chain.reverse();
+        chain
+    }
+
+    // From the root, included, to the node
+    pub(crate) fn nodes_to(&self, index: usize) -> Vec<Node>
+    where
+        Node: Clone,
+    {
+        self.chain(index).into_iter().map(|i| self.visits[i].node.clone()).collect()
+    }
+
+    pub(crate) fn edges_to(&self, index: usize) -> Vec<Edge>
+    where
+        Edge: Clone,
+    {
+        self.chain(index).into_iter()
+            .filter_map(|i| self.visits[i].parent.as_ref().map(|(edge, _)| edge.clone()))
+            .collect()
+    }
+
+    // Every edge with the node it leads to, the root excluded
+    pub(crate) fn path_to(&self, index: usize) -> Vec<(Edge, Node)>
+    where
+        Node: Clone,
+        Edge: Clone,
+    {
+        self.chain(index).into_iter()
+            .filter_map(|i| {
+                let visit = &self.visits[i];
+                visit.parent.as_ref().map(|(edge, _)| (edge.clone(), visit.node.clone()))
+            })
+            .collect()
+    }
+}
+
+impl<Node, Edge> Index<usize> for Arena<Node, Edge> {
+    type Output = Visit<Node, Edge>;
+
+    fn index(&self, index: usize) -> &Visit<Node, Edge> {
+        &self.visits[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::arena::Arena;
+
+    #[test]
+    fn test_paths() {
+        let mut arena = Arena::new();
+        let a = arena.push('a', None);
+        let b = arena.push('b', Some((1, a)));
+        arena.push('x', Some((9, a)));
+        let c = arena.push('c', Some((2, b)));
+
+        assert_eq!(arena[c].depth, 2);
+        assert_eq!(arena.nodes_to(c), vec!['a', 'b', 'c']);
+        assert_eq!(arena.edges_to(c), vec![1, 2]);
+        assert_eq!(arena.path_to(c), vec![(1, 'b'), (2, 'c')]);
+        assert_eq!(arena.path_to(a), vec![]);
+    }
+}
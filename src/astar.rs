@@ -0,0 +1,114 @@
+use crate::arena::Arena;
+use crate::bfs::Graph;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::hash::Hash;
+
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct SearchStats {
+    pub expanded: usize,
+    pub max_frontier: usize,
+}
+
+// Every edge costs 1. The heuristic must not overestimate the remaining edges for the path to be
+// the shortest. Among nodes with the same estimate the one closest to the goal by the heuristic,
+// then the first one discovered, is expanded first.
+pub fn astar<G, F, H>(graph: &G, initial_node: G::Node, mut is_goal: F, heuristic: H)
+    -> (Option<Vec<G::Edge>>, SearchStats)
+where
+    G: Graph,
+    G::Node: Eq + Hash + Clone,
+    G::Edge: Clone,
+    F: FnMut(&G::Node) -> bool,
+    H: Fn(&G::Node) -> usize,
+{
+    let mut stats = SearchStats::default();
+    let mut best: HashMap<G::Node, usize> = HashMap::new();
+    let mut arena: Arena<G::Node, G::Edge> = Arena::new();
+    let mut frontier: BinaryHeap<Reverse<(usize, usize, usize)>> = BinaryHeap::new();
+
+    let h = heuristic(&initial_node);
+    best.insert(initial_node.clone(), 0);
+    let start = arena.push(initial_node, None);
+    frontier.push(Reverse((h, h, start)));
+
+    while let Some(Reverse((_, _, index))) = frontier.pop() {
+        let (node, depth) = (arena[index].node.clone(), arena[index].depth);
+        if best.get(&node) != Some(&depth) {
+            continue;
+        }
+        stats.expanded += 1;
+        if is_goal(&node) {
+            return (Some(arena.edges_to(index)), stats);
+        }
+        for (edge, next_node) in graph.adjacents(&node) {
+            if best.get(&next_node).is_some_and(|d| *d <= depth + 1) {
+                continue;
+            }
+            let h = heuristic(&next_node);
+            best.insert(next_node.clone(), depth + 1);
+            let next_index = arena.push(next_node, Some((edge, index)));
+            frontier.push(Reverse((depth + 1 + h, h, next_index)));
+        }
+        stats.max_frontier = stats.max_frontier.max(frontier.len());
+    }
+    (None, stats)
+}
+
+// Grid heuristics toward a fixed goal, Manhattan for 4 directions, Chebyshev when diagonals count as one move
+pub fn manhattan(goal: (i32, i32)) -> impl Fn(&(i32, i32)) -> usize {
+    move |&(x, y)| ((x - goal.0).abs() + (y - goal.1).abs()) as usize
+}
+
+pub fn chebyshev(goal: (i32, i32)) -> impl Fn(&(i32, i32)) -> usize {
+    move |&(x, y)| (x - goal.0).abs().max((y - goal.1).abs()) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::astar::{astar, chebyshev, manhattan};
+    use crate::bfs::Graph;
+    use crate::bfs_alt::bfs_alt;
+
+    // Open 20x20 grid with a wall on x = 10 from y = 0 to 15
+    struct Grid;
+
+    impl Graph for Grid {
+        type Node = (i32, i32);
+        type Edge = char;
+        type Adjacents = Vec<(char, (i32, i32))>;
+
+        fn adjacents(&self, &(x, y): &(i32, i32)) -> Self::Adjacents {
+            vec![('N', (x, y + 1)), ('S', (x, y - 1)), ('W', (x - 1, y)), ('E', (x + 1, y))]
+                .into_iter()
+                .filter(|(_, (x, y))| (0..20).contains(x) && (0..20).contains(y) && !(*x == 10 && *y <= 15))
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_astar() {
+        let goal = (19, 0);
+        let (path, stats) = astar(&Grid, (0, 0), |n| *n == goal, manhattan(goal));
+        let expected = bfs_alt(&Grid, (0, 0), |_, n| *n == goal).unwrap();
+        assert_eq!(path.unwrap().len(), expected.len());
+        assert!(stats.expanded > 0 && stats.max_frontier > 0);
+
+        // Without a heuristic it is a breadth first search
+        let (_, blind) = astar(&Grid, (0, 0), |n| *n == goal, |_| 0);
+        assert!(stats.expanded < blind.expanded);
+
+        // Ties are broken the same way on every run
+        let (first, _) = astar(&Grid, (0, 0), |n| *n == (5, 5), manhattan((5, 5)));
+        let (second, _) = astar(&Grid, (0, 0), |n| *n == (5, 5), manhattan((5, 5)));
+        assert_eq!(first, second);
+
+        assert_eq!(astar(&Grid, (0, 0), |n| *n == (30, 0), manhattan((30, 0))).0, None);
+    }
+
+    #[test]
+    fn test_heuristics() {
+        assert_eq!(manhattan((1, 2))(&(-2, 6)), 7);
+        assert_eq!(chebyshev((1, 2))(&(-2, 6)), 4);
+    }
+}
@@ -1,8 +1,6 @@
-use std::borrow::Borrow;
-use std::collections::{HashMap, HashSet, VecDeque};
-use std::fmt::Debug;
-use std::hash::{Hash, Hasher};
-use std::rc::{Rc, Weak};
+use crate::arena::Arena;
+use std::collections::HashMap;
+use std::hash::Hash;
 
 pub trait Graph {
     type Node;
@@ -12,128 +10,249 @@ pub trait Graph {
     fn adjacents(&self, node: &Self::Node) -> Self::Adjacents;
 }
 
-
-#[derive(Debug)]
-pub struct NodeHolder<Node, Edge> {
+#[derive(Debug, Clone)]
+pub struct Visit<Node, Edge> {
     pub node: Node,
-    pub parent: Option<(Edge, NodeHolderRef<Node, Edge>)>,
+    // Index of the parent in the explorer and the edge from it, None for the starts
+    pub parent: Option<(Edge, usize)>,
     pub depth: usize,
 }
 
-impl<Node, Edge> NodeHolder<Node, Edge> {
-    pub fn new(node: Node, parent: Option<(Edge, NodeHolderRef<Node, Edge>)>, depth: usize) -> Self {
-        NodeHolder { node, parent, depth }
-    }
+// Arena of the visited nodes in breadth first order, which is also the queue of the traversal:
+// the nodes after `expanded` are discovered but their adjacents are not.
+#[derive(Debug)]
+pub struct GraphExplorer<G: Graph>
+where
+    G::Node: Eq + Hash,
+{
+    visits: Arena<G::Node, G::Edge>,
+    indices: HashMap<G::Node, usize>,
+    expanded: usize,
 }
 
-#[derive(Debug)]
-pub struct NodeHolderRef<Node, Edge>(pub Rc<NodeHolder<Node, Edge>>);
+impl<G: Graph> GraphExplorer<G>
+where
+    G::Node: Eq + Hash + Clone,
+{
+    pub fn new<I: IntoIterator<Item = G::Node>>(starts: I) -> Self {
+        let mut explorer = GraphExplorer { visits: Arena::new(), indices: HashMap::new(), expanded: 0 };
+        for start in starts {
+            explorer.discover(start, None);
+        }
+        explorer
+    }
 
-impl<Node, Edge> NodeHolderRef<Node, Edge> {
-    fn weak(&self) -> Weak<NodeHolder<Node, Edge>> {
-        Rc::downgrade(&self.0)
+    fn discover(&mut self, node: G::Node, parent: Option<(G::Edge, usize)>) {
+        if !self.indices.contains_key(&node) {
+            self.indices.insert(node.clone(), self.visits.len());
+            self.visits.push(node, parent);
+        }
+    }
+
+    // Index of the next node to expand
+    pub fn peek(&self) -> Option<usize> {
+        Some(self.expanded).filter(|index| *index < self.visits.len())
+    }
+
+    // Discovers the adjacents of the next node, returns its index
+    pub fn expand(&mut self, graph: &G) -> Option<usize> {
+        let index = self.peek()?;
+        for (edge, next_node) in graph.adjacents(&self.visits[index].node) {
+            self.discover(next_node, Some((edge, index)));
+        }
+        self.expanded += 1;
+        Some(index)
     }
-}
 
-impl<Node, Edge> Clone for NodeHolderRef<Node, Edge> {
-    fn clone(&self) -> Self {
-        NodeHolderRef(self.0.clone())
+    pub fn visits(&self) -> &[Visit<G::Node, G::Edge>] {
+        self.visits.visits()
+    }
+
+    pub fn len(&self) -> usize {
+        self.visits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.visits().is_empty()
+    }
+
+    pub fn contains(&self, node: &G::Node) -> bool {
+        self.indices.contains_key(node)
+    }
+
+    pub fn index_of(&self, node: &G::Node) -> Option<usize> {
+        self.indices.get(node).copied()
+    }
+
+    pub fn depth(&self, node: &G::Node) -> Option<usize> {
+        self.index_of(node).map(|index| self.visits[index].depth)
+    }
+
+    // From the start the node was reached from, included, to the node
+    pub fn nodes_to(&self, index: usize) -> Vec<G::Node> {
+        self.visits.nodes_to(index)
+    }
+
+    pub fn edges_to(&self, index: usize) -> Vec<G::Edge>
+    where
+        G::Edge: Clone,
+    {
+        self.visits.edges_to(index)
+    }
+
+    pub fn path_to(&self, index: usize) -> Path<G>
+    where
+        G::Edge: Clone,
+    {
+        self.visits.path_to(index)
     }
 }
 
-impl<Node, Edge> PartialEq for NodeHolderRef<Node, Edge>
+pub type Path<G> = Vec<(<G as Graph>::Edge, <G as Graph>::Node)>;
+pub type ParentTree<G> = HashMap<<G as Graph>::Node, Option<(<G as Graph>::Edge, <G as Graph>::Node)>>;
+
+// Breadth first traversal from several starts until a node satisfies `is_done`, returns its index
+pub fn search_from<G, I, F>(graph: &G, starts: I, mut is_done: F) -> (GraphExplorer<G>, Option<usize>)
 where
-    Node: Eq,
+    G: Graph,
+    G::Node: Eq + Hash + Clone,
+    I: IntoIterator<Item = G::Node>,
+    F: FnMut(&G, &G::Node) -> bool,
 {
-    fn eq(&self, other: &Self) -> bool {
-        self.0.node == other.0.node
+    let mut explorer = GraphExplorer::new(starts);
+    while let Some(index) = explorer.peek() {
+        if is_done(graph, &explorer.visits[index].node) {
+            return (explorer, Some(index));
+        }
+        explorer.expand(graph);
     }
+    (explorer, None)
 }
 
-impl<Node, Edge> Eq for NodeHolderRef<Node, Edge> where Node: Eq {}
-
-impl<Node, Edge> Hash for NodeHolderRef<Node, Edge>
+pub fn search<G, F>(graph: &G, initial_node: G::Node, is_done: F) -> (GraphExplorer<G>, Option<usize>)
 where
-    Node: Hash,
+    G: Graph,
+    G::Node: Eq + Hash + Clone,
+    F: FnMut(&G, &G::Node) -> bool,
 {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.0.node.hash(state)
-    }
+    search_from(graph, Some(initial_node), is_done)
 }
 
-impl<Node, Edge> Borrow<Node> for NodeHolderRef<Node, Edge> {
-    fn borrow(&self) -> &Node {
-        &self.0.node
-    }
+pub fn bfs<G, F>(graph: &G, initial_node: G::Node, is_done: F) -> (GraphExplorer<G>, Option<Path<G>>)
+where
+    G: Graph,
+    G::Node: Eq + Hash + Clone,
+    G::Edge: Clone,
+    F: Fn(&G::Node) -> bool,
+{
+    let (explorer, found) = search(graph, initial_node, |_, node| is_done(node));
+    let path = found.map(|index| explorer.path_to(index));
+    (explorer, path)
 }
 
-#[derive(Debug)]
-pub struct GraphExplorer<G: Graph>
+// Every node reachable from one of the starts, with its number of edges from the closest start
+pub fn bfs_distances_from<G, I>(graph: &G, starts: I) -> HashMap<G::Node, usize>
 where
-    G::Node: Eq + Hash,
+    G: Graph,
+    G::Node: Eq + Hash + Clone,
+    I: IntoIterator<Item = G::Node>,
 {
-    pub cache: HashSet<NodeHolderRef<G::Node, G::Edge>>,
-    queue: VecDeque<NodeHolderRef<G::Node, G::Edge>>,
+    let (explorer, _) = search_from(graph, starts, |_, _| false);
+    explorer.visits.into_visits().into_iter().map(|visit| (visit.node, visit.depth)).collect()
 }
 
-impl<G: Graph> GraphExplorer<G>
+pub fn bfs_distances<G>(graph: &G, start: G::Node) -> HashMap<G::Node, usize>
 where
-    G::Node: Eq + Hash,
+    G: Graph,
+    G::Node: Eq + Hash + Clone,
 {
-    fn new() -> GraphExplorer<G> {
-        GraphExplorer {
-            cache: HashSet::new(),
-            queue: VecDeque::new(),
-        }
-    }
+    bfs_distances_from(graph, Some(start))
+}
 
-    fn enqueue(&mut self, n: NodeHolder<G::Node, G::Edge>) {
-        let node_rc = NodeHolderRef(Rc::new(n));
-        self.cache.insert(node_rc.clone());
-        self.queue.push_back(node_rc);
-    }
+// Farthest node from the start and its distance, the first one reached on ties
+pub fn eccentricity<G>(graph: &G, start: G::Node) -> (G::Node, usize)
+where
+    G: Graph,
+    G::Node: Eq + Hash + Clone,
+{
+    let (explorer, _) = search(graph, start, |_, _| false);
+    let max = explorer.visits().last().map_or(0, |visit| visit.depth);
+    let farthest = explorer.visits.into_visits().into_iter().find(|visit| visit.depth == max).expect("The start is always visited");
+    (farthest.node, max)
 }
 
-pub fn bfs<G, F>(graph: &G, initial_node: G::Node, is_done: F) ->
-(GraphExplorer<G>, Option<Vec<(G::Edge, G::Node)>>)
+// Parent of every reachable node in the breadth first tree, with the edge leading from it.
+// The start has no parent.
+pub fn bfs_tree<G>(graph: &G, start: G::Node) -> ParentTree<G>
 where
     G: Graph,
-    G::Node: Eq + Hash + Clone + Debug,
-    G::Edge: Clone + Debug,
-    F: Fn(&G::Node) -> bool,
+    G::Node: Eq + Hash + Clone,
+    G::Edge: Clone,
 {
-    let mut explorer: GraphExplorer<G> = GraphExplorer::new();
+    let (explorer, _) = search(graph, start, |_, _| false);
+    explorer.visits().iter()
+        .map(|visit| {
+            let parent = visit.parent.as_ref().map(|(edge, i)| (edge.clone(), explorer.visits[*i].node.clone()));
+            (visit.node.clone(), parent)
+        })
+        .collect()
+}
 
-    explorer.enqueue(NodeHolder::new(initial_node, None, 0));
-    while let Some(node_holder) = explorer.queue.pop_front() {
-        if is_done(&node_holder.0.node) {
-            //            println!("Explored states : {}", explorer.cache.len());
-            return (explorer, Some(backtrack(node_holder)));
-        }
-        for (edge, next_node) in graph.adjacents(&node_holder.0.node) {
-            if !explorer.cache.contains(&next_node) {
-                explorer.enqueue(NodeHolder::new(
-                    next_node,
-                    Some((edge, node_holder.clone())),
-                    node_holder.0.depth + 1
-                ));
-            }
+#[cfg(test)]
+mod tests {
+    use crate::bfs::{bfs, bfs_distances, bfs_distances_from, bfs_tree, eccentricity, search, Graph};
+    use crate::bfs_alt::bfs_alt;
+
+    // Path 0 - 1 - 2 - 3 - 4 with a branch 1 - 5
+    struct Tree;
+
+    impl Graph for Tree {
+        type Node = u32;
+        type Edge = ();
+        type Adjacents = Vec<((), u32)>;
+
+        fn adjacents(&self, node: &u32) -> Vec<((), u32)> {
+            let edges = [(0, 1), (1, 2), (2, 3), (3, 4), (1, 5)];
+            edges.iter()
+                .filter_map(|&(a, b)| if a == *node { Some(b) } else if b == *node { Some(a) } else { None })
+                .map(|n| ((), n))
+                .collect()
         }
     }
-    (explorer, None)
-}
 
-fn backtrack<Node, Edge>(holder: NodeHolderRef<Node, Edge>) -> Vec<(Edge, Node)>
-where
-    Node: Clone,
-    Edge: Clone,
-{
-    let mut track: Vec<(Edge, Node)> = Vec::new();
-    let mut current = holder;
-    while let Some(parent_ref) = current.0.parent.as_ref() {
-        track.push((parent_ref.0.clone(), current.0.node.clone()));
-        current = parent_ref.1.clone();
-    }
-    track.reverse();
-    track
+    #[test]
+    fn test_search() {
+        let (explorer, found) = search(&Tree, 0, |_, n| *n == 4);
+        let index = found.unwrap();
+        assert_eq!(explorer.nodes_to(index), vec![0, 1, 2, 3, 4]);
+        assert_eq!(explorer.edges_to(index).len(), 4);
+        assert_eq!(explorer.depth(&5), Some(2));
+        assert!(explorer.contains(&3) && !explorer.contains(&6));
+
+        let (_, path) = bfs(&Tree, 0, |n| *n == 5);
+        assert_eq!(path, Some(vec![((), 1), ((), 5)]));
+        assert_eq!(bfs_alt(&Tree, 0, |_, n| *n == 5), Some(vec![(), ()]));
+        assert_eq!(bfs_alt(&Tree, 0, |_, n| *n == 6), None);
+    }
+
+    #[test]
+    fn test_distances() {
+        let distances = bfs_distances(&Tree, 0);
+        assert_eq!(distances.len(), 6);
+        assert_eq!((distances[&4], distances[&5]), (4, 2));
+
+        let distances = bfs_distances_from(&Tree, vec![0, 4]);
+        assert_eq!((distances[&2], distances[&3], distances[&5]), (2, 1, 2));
+
+        assert_eq!(eccentricity(&Tree, 0), (4, 4));
+        assert_eq!(eccentricity(&Tree, 2), (0, 2));
+    }
+
+    #[test]
+    fn test_tree() {
+        let parents = bfs_tree(&Tree, 2);
+        assert_eq!(parents[&2], None);
+        assert_eq!(parents[&5], Some(((), 1)));
+        assert_eq!(parents[&0], Some(((), 1)));
+    }
 }
@@ -0,0 +1,178 @@
+use crate::arena::Arena;
+use crate::bfs::Graph;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+// A graph that can also be walked backwards. `predecessors` returns the nodes with an edge toward
+// the node, with that edge. By default every edge can be walked both ways with the same label.
+pub trait ReversibleGraph: Graph {
+    fn predecessors(&self, node: &Self::Node) -> Self::Adjacents {
+        self.adjacents(node)
+    }
+}
+
+// Visited nodes of one direction, the backward side grows from the goal through predecessors
+struct Side<Node, Edge> {
+    visits: Arena<Node, Edge>,
+    indices: HashMap<Node, usize>,
+    frontier: Vec<usize>,
+}
+
+impl<Node: Eq + Hash + Clone, Edge> Side<Node, Edge> {
+    fn new(start: Node) -> Self {
+        let mut visits = Arena::new();
+        let index = visits.push(start.clone(), None);
+        let mut indices = HashMap::new();
+        indices.insert(start, index);
+        Side { visits, indices, frontier: vec![index] }
+    }
+}
+
+// Alternates breadth first levels from both ends, always the side with the smaller frontier, and
+// stops at the level where they meet. Returns the edges from the start to the goal.
+pub fn bidirectional_bfs<G>(graph: &G, start: G::Node, goal: G::Node) -> Option<Vec<G::Edge>>
+where
+    G: ReversibleGraph,
+    G::Node: Eq + Hash + Clone,
+    G::Edge: Clone,
+{
+    if start == goal {
+        return Some(Vec::new());
+    }
+    let mut forward = Side::new(start);
+    let mut backward = Side::new(goal);
+
+    while !forward.frontier.is_empty() && !backward.frontier.is_empty() {
+        let is_forward = forward.frontier.len() <= backward.frontier.len();
+        let (side, other) = if is_forward { (&mut forward, &backward) } else { (&mut backward, &forward) };
+
+        // Closest meeting point through the other side: (its depth there, index here, index there)
+        let mut meeting: Option<(usize, usize, usize)> = None;
+        let mut next = Vec::new();
+        for index in std::mem::take(&mut side.frontier) {
+            let adjacents = if is_forward {
+                graph.adjacents(&side.visits[index].node)
+            } else {
+                graph.predecessors(&side.visits[index].node)
+            };
+            for (edge, node) in adjacents {
+                if side.indices.contains_key(&node) {
+                    continue;
+                }
+                let next_index = side.visits.push(node.clone(), Some((edge, index)));
+                if let Some(&other_index) = other.indices.get(&node) {
+                    let other_depth = other.visits[other_index].depth;
+                    if meeting.is_none_or(|(best, _, _)| other_depth < best) {
+                        meeting = Some((other_depth, next_index, other_index));
+                    }
+                }
+                side.indices.insert(node, next_index);
+                next.push(next_index);
+            }
+        }
+        side.frontier = next;
+
+        if let Some((_, here, there)) = meeting {
+            let (forward_index, backward_index) = if is_forward { (here, there) } else { (there, here) };
+            let mut path = forward.visits.edges_to(forward_index);
+            path.extend(backward.visits.edges_to(backward_index).into_iter().rev());
+            return Some(path);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bfs::Graph;
+    use crate::bfs_alt::bfs_alt;
+    use crate::bidirectional::{bidirectional_bfs, ReversibleGraph};
+
+    // Infinite binary tree, node n has the children 2n and 2n + 1
+    struct Binary;
+
+    impl Graph for Binary {
+        type Node = u64;
+        type Edge = char;
+        type Adjacents = Vec<(char, u64)>;
+
+        fn adjacents(&self, node: &u64) -> Vec<(char, u64)> {
+            vec![('L', node * 2), ('R', node * 2 + 1)]
+        }
+    }
+
+    impl ReversibleGraph for Binary {
+        fn predecessors(&self, node: &u64) -> Vec<(char, u64)> {
+            match node {
+                1 => vec![],
+                n => vec![(if n % 2 == 0 { 'L' } else { 'R' }, n / 2)],
+            }
+        }
+    }
+
+    // 30x30 grid, walking backwards a node is reached with the opposite direction
+    struct Grid;
+
+    impl Graph for Grid {
+        type Node = (i32, i32);
+        type Edge = char;
+        type Adjacents = Vec<(char, (i32, i32))>;
+
+        fn adjacents(&self, &(x, y): &(i32, i32)) -> Self::Adjacents {
+            vec![('N', (x, y + 1)), ('S', (x, y - 1)), ('W', (x - 1, y)), ('E', (x + 1, y))]
+                .into_iter()
+                .filter(|(_, (x, y))| (0..30).contains(x) && (0..30).contains(y))
+                .collect()
+        }
+    }
+
+    impl ReversibleGraph for Grid {
+        fn predecessors(&self, node: &(i32, i32)) -> Self::Adjacents {
+            let opposite = |edge| match edge {
+                'N' => 'S',
+                'S' => 'N',
+                'W' => 'E',
+                _ => 'W',
+            };
+            self.adjacents(node).into_iter().map(|(edge, n)| (opposite(edge), n)).collect()
+        }
+    }
+
+    // Path 0 - 1 - 2 - 3 with unlabelled edges, the same both ways
+    struct Line;
+
+    impl Graph for Line {
+        type Node = u32;
+        type Edge = ();
+        type Adjacents = Vec<((), u32)>;
+
+        fn adjacents(&self, node: &u32) -> Vec<((), u32)> {
+            vec![node.wrapping_sub(1), node + 1].into_iter().filter(|n| *n < 4).map(|n| ((), n)).collect()
+        }
+    }
+
+    impl ReversibleGraph for Line {}
+
+    #[test]
+    fn test_directed() {
+        // Only the backward side makes progress on an infinite forward frontier
+        assert_eq!(bidirectional_bfs(&Binary, 1, 13), Some(vec!['R', 'L', 'R']));
+        assert_eq!(bidirectional_bfs(&Binary, 2, 2), Some(vec![]));
+        assert_eq!(bidirectional_bfs(&Binary, 2, 3), None);
+    }
+
+    #[test]
+    fn test_symmetric() {
+        let path = bidirectional_bfs(&Grid, (0, 0), (29, 17)).unwrap();
+        assert_eq!(path.len(), bfs_alt(&Grid, (0, 0), |_, n| *n == (29, 17)).unwrap().len());
+        let end = path.iter().fold((0, 0), |(x, y), edge| match edge {
+            'N' => (x, y + 1),
+            'S' => (x, y - 1),
+            'W' => (x - 1, y),
+            _ => (x + 1, y),
+        });
+        assert_eq!(end, (29, 17));
+
+        assert_eq!(bidirectional_bfs(&Line, 3, 0), Some(vec![(); 3]));
+    }
+}
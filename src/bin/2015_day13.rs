@@ -87,12 +87,13 @@ fn solve_part_2(input: &str) -> i32 {
 fn main() {
     let input = include_str!("../inputs/2015/day13.txt");
 
-    let max1 = solve_part_1(&input);
-    let max2 = solve_part_2(&input);
+    let max1 = solve_part_1(input);
+    let max2 = solve_part_2(input);
 
     println!("{} {}", max1, max2);
 }
 
+#[allow(dead_code)]
 const SAMPLE: &str = "Alice would gain 54 happiness units by sitting next to Bob.
 Alice would lose 79 happiness units by sitting next to Carol.
 Alice would lose 2 happiness units by sitting next to David.
@@ -1,5 +1,6 @@
 #[derive(Debug)]
 struct Ingredient {
+    #[allow(dead_code)]
     name: String,
     capacity: i32,
     durability: i32,
@@ -25,7 +26,7 @@ fn parse_ingredients(input: &str) -> Vec<Ingredient> {
             let name = parts[0];
             let props: Vec<&str> = parts[1].split(",").collect();
             let props: Vec<i32> = props.iter()
-                .map(|p| p.trim().split_whitespace().nth(1).unwrap())
+                .map(|p| p.split_whitespace().nth(1).unwrap())
                 .map(|p| p.parse::<i32>().unwrap())
                 .collect();
 
@@ -56,7 +57,7 @@ fn score(ingredients: &[Ingredient], coefficients: &Coefficients) -> i32 {
                 .sum();
 
             if total >= 0 { total } else { 0 }
-        }).fold(1, |acc, i| acc * i)
+        }).product()
 }
 
 fn calories(ingredients: &[Ingredient], coefficients: &Coefficients) -> i32 {
@@ -26,7 +26,7 @@ fn main() {
 
 
     let res = sues.iter()
-        .find(|(_, inv)| to_set(&inv).is_subset(&inventory_set));
+        .find(|(_, inv)| to_set(inv).is_subset(&inventory_set));
     println!("{:?}", res);
 
     let res = sues.iter()
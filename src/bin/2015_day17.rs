@@ -1,5 +1,4 @@
-use itertools::{Itertools, MultiProduct};
-use std::ops::RangeInclusive;
+use itertools::Itertools;
 
 fn main() {
     let containers = parse_containers(include_str!("../inputs/2015/day17.txt"));
@@ -29,9 +28,9 @@ fn get_all_indices(n: usize) -> impl Iterator<Item=Vec<u32>> {
         .multi_cartesian_product()
 }
 
-fn get_combinations(containers: &Vec<u32>) -> Vec<(u32, u32)> {
+fn get_combinations(containers: &[u32]) -> Vec<(u32, u32)> {
     get_all_indices(containers.len())
-        .map(|i| (i.iter().sum::<u32>(), total_volume(&containers, &i)))
+        .map(|i| (i.iter().sum::<u32>(), total_volume(containers, &i)))
         .filter(|(_, v)| *v == 150)
         .collect_vec()
 }
@@ -1,7 +1,6 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 use aoc2019::bfs::Graph;
-use aoc2019::bfs_alt::bfs_alt;
-use itertools::Itertools;
+use aoc2019::heuristic::best_first;
 
 fn parse_replacements(input: &str) -> Replacements {
     Replacements(input.lines()
@@ -50,11 +49,13 @@ fn main() {
     println!("{}", all_replacements.len());
 
     let search_graph = SearchGraph::new(&replacements);
-    let path = bfs_alt(&search_graph, medicine.to_string(), |_, m| {
-        m == "e"
-    });
-    if let Some(path) = path {
-        println!("{:?}", path.len());
+    // Greedily reduces the shortest molecule first, so the path is only as short as a heuristic
+    // guarantees. The input is built so that any reduction to e takes the same number of steps.
+    // A bidirectional BFS does not help: its levels grow about 30 times from the medicine and 6 times
+    // from e, and the two sides would each need around 100 levels to meet.
+    let found = best_first(&search_graph, medicine.to_string(), |m| m == "e", |m| m.len());
+    if let Some(found) = found {
+        println!("{:?} ({:?})", found.path.len(), found.guarantee);
     } else {
         println!("Not found");
     }
@@ -75,22 +76,10 @@ impl<'a> Graph for SearchGraph<'a> {
     type Edge = ();
     type Adjacents = Vec<((), String)>;
 
+    // Every molecule the node can be made from with one replacement
     fn adjacents(&self, node: &Self::Node) -> Self::Adjacents {
-        let subs = self.replacement.get_all_reverse_replacements(node.as_str())
-            .collect_vec();
-
-        if subs.is_empty() {
-            vec![]
-        } else {
-            let min = subs.iter().map(|s| s.len()).min().unwrap();
-            subs.into_iter()
-                .filter(|s| s.len() == min)
-                .map(|s| ((), s))
-                .take(2)
-                .collect()
-        }
-
+        self.replacement.get_all_reverse_replacements(node.as_str())
+            .map(|s| ((), s))
+            .collect()
     }
-
-
-}
\ No newline at end of file
+}
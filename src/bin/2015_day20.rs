@@ -1,15 +1,16 @@
 use num_integer::Roots;
 
+#[allow(dead_code)]
 fn compute_present(house_number: u32) -> u32 {
     (1 ..= house_number)
-        .filter(|i| house_number % i == 0)
+        .filter(|i| house_number.is_multiple_of(*i))
         .map(|i| i * 10).sum()
 }
 
 fn compute_present_opt(house_number: u32) -> u32 {
     let mut presents = 0;
     for i in 1 ..= house_number.sqrt() {
-        if house_number % i == 0 {
+        if house_number.is_multiple_of(i) {
             let q = house_number / i;
             if q == i {
                 presents += i * 10
@@ -24,7 +25,7 @@ fn compute_present_opt(house_number: u32) -> u32 {
 fn compute_present_part_2(house_number: u32) -> u32 {
     let mut presents = 0;
     for i in 1 ..= house_number.sqrt() {
-        if house_number % i == 0 {
+        if house_number.is_multiple_of(i) {
             let q = house_number / i;
             if q == i {
                 if q <= 50 {
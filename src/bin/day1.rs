@@ -26,6 +26,7 @@ fn fuel_for_mass_advanced(mass: u32) -> u32 {
     } else { 0 }
 }
 
+#[cfg(test)]
 mod tests {
     use crate::{fuel_for_mass, fuel_for_mass_advanced};
 
@@ -90,7 +90,7 @@ fn build_targets<'a>(best_asteroid: &Asteroid, asteroids: &'a [Asteroid]) -> Vec
         ).collect_vec()
 }
 
-fn compute_depths(targets: &mut Vec<Target>) {
+fn compute_depths(targets: &mut [Target]) {
     for i in 0..targets.len() - 1 {
         let a = &targets[i];
         let angle = a.angle;
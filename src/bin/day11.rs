@@ -1,40 +1,11 @@
 use aoc2019::intcode::{IntcodeCpu, Int, parse_intcode_program};
-use crate::Color::Black;
-use std::collections::{HashSet, HashMap};
+use aoc2019::intcode::protocol::{Color, Decoder, Paint, PaintProtocol, Turn};
+use std::collections::HashMap;
 
 #[derive(Clone)]
 struct DrawingProgram {
-    cpu: IntcodeCpu
-}
-
-#[derive(Eq, PartialEq, Debug, Copy, Clone)]
-enum Color {
-    Black,
-    White,
-}
-
-impl Color {
-    fn parse(v: u8) -> Option<Color> {
-        match v {
-            0 => Some(Color::Black),
-            1 => Some(Color::White),
-            _ => None
-        }
-    }
-}
-
-enum Turn {
-    Left, Right
-}
-
-impl Turn {
-    fn parse(v: u8) -> Option<Turn> {
-        match v {
-            0 => Some(Turn::Left),
-            1 => Some(Turn::Right),
-            _ => None
-        }
-    }
+    cpu: IntcodeCpu,
+    decoder: Decoder<PaintProtocol>,
 }
 
 enum Direction {
@@ -64,15 +35,14 @@ impl DrawingProgram {
     fn next(&mut self, current: Color) -> Option<(Color, Turn)> {
         self.cpu.inputs.push_back(current as Int);
         self.cpu.run();
-        if self.cpu.outputs.len() == 2 {
-            let color = Color::parse(self.cpu.outputs[0] as u8).unwrap();
-            let turn = Turn::parse(self.cpu.outputs[1] as u8).unwrap();
-            self.cpu.outputs.clear();
-            Some((color, turn))
-        } else if self.cpu.is_halted {
-            None
-        } else {
-            panic!("illegal state")
+        let events = self.decoder.drain(&mut self.cpu).expect("Invalid paint output");
+        match events.as_slice() {
+            [Paint { color, turn }] => Some((*color, *turn)),
+            [] if self.cpu.is_halted => {
+                self.decoder.finish().expect("Invalid paint output");
+                None
+            }
+            _ => panic!("illegal state")
         }
     }
 }
@@ -194,8 +164,8 @@ fn main() {
 
 fn build_drawing_program() -> DrawingProgram {
     let program_src = include_str!("../inputs/day11.txt");
-    let program = parse_intcode_program(program_src);
+    let program = parse_intcode_program(program_src).expect("Invalid Intcode program");
     let cpu = IntcodeCpu::new_with_inputs_and_large_mem(64 * 1024, program, vec![]);
-    let drawing_program = DrawingProgram { cpu };
-    drawing_program
+    
+    DrawingProgram { cpu, decoder: Decoder::new(PaintProtocol) }
 }
\ No newline at end of file
@@ -1,9 +1,7 @@
 use aoc2019::vec3d::Vec3d;
 use std::cell::RefCell;
-use std::borrow::BorrowMut;
-use std::collections::HashSet;
 use itertools::Itertools;
-use num_integer::{gcd, gcd_lcm, lcm};
+use num_integer::lcm;
 
 type Int = i32;
 
@@ -58,7 +56,7 @@ fn apply_gravity(moon_a: &mut Moon, moon_b: &mut Moon) {
     moon_b.velocity -= gravity_vec;
 }
 
-fn run_step(moons: &Vec<RefCell<Moon>>) {
+fn run_step(moons: &[RefCell<Moon>]) {
     for i in 0..moons.len() {
         for j in (i + 1)..moons.len() {
             apply_gravity(&mut moons.get(i).unwrap().borrow_mut(),
@@ -68,41 +66,41 @@ fn run_step(moons: &Vec<RefCell<Moon>>) {
     moons.iter().for_each(|m| m.borrow_mut().apply_velocity())
 }
 
-fn solve_part_1(moons: &Vec<RefCell<Moon>>, steps: u32) -> i32 {
+fn solve_part_1(moons: &[RefCell<Moon>], steps: u32) -> i32 {
     for _ in 0..steps { run_step(moons) }
     moons.iter()
         .map(|m| m.borrow().energy())
         .sum()
 }
 
-fn solve_part_2(moons: &Vec<RefCell<Moon>>) -> u64 {
+fn solve_part_2(moons: &[RefCell<Moon>]) -> u64 {
     let (period_x, period_y, period_z) = find_periods(moons);
     println!("{} {} {}", period_x, period_y, period_z);
     lcm(lcm(period_x, period_y), period_z)
 }
 
-fn get_x_state(moons: &Vec<RefCell<Moon>>) -> Vec<(i32, i32)> {
+fn get_x_state(moons: &[RefCell<Moon>]) -> Vec<(i32, i32)> {
     moons.iter().map(|m| {
         let m = m.borrow();
         (m.position.x, m.velocity.x)
     }).collect_vec()
 }
 
-fn get_y_state(moons: &Vec<RefCell<Moon>>) -> Vec<(i32, i32)> {
+fn get_y_state(moons: &[RefCell<Moon>]) -> Vec<(i32, i32)> {
     moons.iter().map(|m| {
         let m = m.borrow();
         (m.position.y, m.velocity.y)
     }).collect_vec()
 }
 
-fn get_z_state(moons: &Vec<RefCell<Moon>>) -> Vec<(i32, i32)> {
+fn get_z_state(moons: &[RefCell<Moon>]) -> Vec<(i32, i32)> {
     moons.iter().map(|m| {
         let m = m.borrow();
         (m.position.z, m.velocity.z)
     }).collect_vec()
 }
 
-fn find_periods(moons: &Vec<RefCell<Moon>>) -> (u64, u64, u64) {
+fn find_periods(moons: &[RefCell<Moon>]) -> (u64, u64, u64) {
     let init_x_state = get_x_state(moons);
     let init_y_state = get_y_state(moons);
     let init_z_state = get_z_state(moons);
@@ -151,7 +149,7 @@ fn main() {
 #[cfg(test)]
 mod tests {
     use std::cell::RefCell;
-    use crate::{Moon, run_step, solve_part_1};
+    use crate::{Moon, solve_part_1};
 
     #[test]
     fn test_example() {
@@ -1,24 +1,26 @@
-use std::fmt::{Display, Error, Formatter};
-use std::mem::swap;
-use std::ptr::replace;
-use std::thread::sleep;
-use std::time::Duration;
-
-use itertools::Itertools;
+use std::env;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
 
 use aoc2019::intcode::{Int, IntcodeCpu, parse_intcode_program};
+use aoc2019::intcode::patch::{find_patch_set, parse_patch_sets};
+use aoc2019::intcode::protocol::{ArcadeEvent, ArcadeProtocol, Decoder, Tile};
+use aoc2019::intcode::session::{load_session, replay, save_session, Recorder};
 
 fn part1(input: &str) -> usize {
-    let mut cpu = IntcodeCpu::new_with_inputs_and_large_mem(64_000, parse_intcode_program(input), vec![]);
+    let mut cpu = IntcodeCpu::new_with_inputs_and_large_mem(64_000, parse_intcode_program(input).expect("Invalid Intcode program"), vec![]);
     cpu.run();
-    cpu.outputs.chunks(3)
-        .filter(|v| v[2] == 2)
+    Decoder::new(ArcadeProtocol).drain(&mut cpu).expect("Invalid arcade output")
+        .iter()
+        .filter(|e| matches!(e, ArcadeEvent::TileDraw { tile: Tile::Block, .. }))
         .count()
 }
 
 struct Game {
     cpu: IntcodeCpu,
-    buffer: [[Int; 38]; 21],
+    recorder: Recorder,
+    decoder: Decoder<ArcadeProtocol>,
+    buffer: [[Tile; 38]; 21],
     paddle: (Int, Int),
     ball: (Int, Int),
     score: Int,
@@ -26,10 +28,14 @@ struct Game {
 
 impl Game {
     pub fn new(mut cpu: IntcodeCpu) -> Self {
-        cpu.memory[0] = 2;
+        let sets = parse_patch_sets(include_str!("../inputs/day13_patches.txt")).expect("Invalid patch sets");
+        find_patch_set(&sets, "free_play").expect("Missing patch set")
+            .apply(&mut cpu).expect("Patch does not fit the program");
         Game {
             cpu,
-            buffer: [[0; 38]; 21],
+            recorder: Recorder::new(),
+            decoder: Decoder::new(ArcadeProtocol),
+            buffer: [[Tile::Empty; 38]; 21],
             paddle: (0, 0),
             ball: (0, 0),
             score: 0,
@@ -37,28 +43,24 @@ impl Game {
     }
 
     fn start(&mut self) {
-        self.cpu.run();
+        self.recorder.run(&mut self.cpu);
         self.process_outputs();
     }
 
     fn process_outputs(&mut self) {
-        let outputs = std::mem::replace(&mut self.cpu.outputs, vec![]);
-        if let Some(player_pos) = outputs.chunks(3).find(|c| c[0] != -1 && c[2] == 3) {
-            self.paddle = (player_pos[0], player_pos[1]);
-        }
-        if let Some(ball_pos) = outputs.chunks(3).find(|c| c[0] != -1 && c[2] == 4) {
-            self.ball = (ball_pos[0], ball_pos[1]);
-        }
-
-        if let Some(led) = outputs.chunks(3).find(|c| c[0] == -1) {
-            self.score = led[2];
-        }
-
-        outputs.chunks(3).for_each(|c| {
-            if c[0] != -1 {
-                self.buffer[c[1] as usize][c[0] as usize] = c[2];
+        for event in self.decoder.drain(&mut self.cpu).expect("Invalid arcade output") {
+            match event {
+                ArcadeEvent::Score(score) => self.score = score,
+                ArcadeEvent::TileDraw { x, y, tile } => {
+                    match tile {
+                        Tile::Paddle => self.paddle = (x, y),
+                        Tile::Ball => self.ball = (x, y),
+                        _ => {}
+                    }
+                    self.buffer[y as usize][x as usize] = tile;
+                }
             }
-        });
+        }
     }
 
     fn play(&mut self, direction: Int) {
@@ -81,21 +83,24 @@ impl Display for Game {
     }
 }
 
-fn char_for_code(c: Int) -> char {
+fn char_for_code(c: Tile) -> char {
     match c {
-        1 => '#',
-        2 => '@',
-        3 => '=',
-        4 => 'o',
-        _ => ' '
+        Tile::Wall => '#',
+        Tile::Block => '@',
+        Tile::Paddle => '=',
+        Tile::Ball => 'o',
+        Tile::Empty => ' '
     }
 }
 
 
-fn part2(input: &str) {
-    let program = parse_intcode_program(input);
-    let cpu = IntcodeCpu::new_with_inputs_and_large_mem(8000, program, vec![]);
-    let mut game = Game::new(cpu);
+fn new_game(input: &str) -> Game {
+    let program = parse_intcode_program(input).expect("Invalid Intcode program");
+    Game::new(IntcodeCpu::new_with_inputs_and_large_mem(8000, program, vec![]))
+}
+
+fn part2(input: &str, record: Option<&Path>) {
+    let mut game = new_game(input);
     game.start();
 
     while !game.cpu.is_halted {
@@ -108,13 +113,40 @@ fn part2(input: &str) {
         // sleep(Duration::from_millis(16));
     }
     println!("{}", game);
+    if let Some(path) = record {
+        save_session(path, game.recorder.session()).expect("Cannot save the session");
+    }
+}
+
+// Plays a recorded session again instead of running the autopilot
+fn replay_session(input: &str, path: &Path) {
+    let session = load_session(path).expect("Cannot load the session");
+    let mut game = new_game(input);
+    match replay(game.cpu.fork(), &session, usize::MAX) {
+        Ok(cpu) => {
+            game.cpu = cpu;
+            game.process_outputs();
+            println!("{}", game);
+        }
+        Err(divergence) => println!("Replay diverged: {}", divergence),
+    }
 }
 
 
 fn main() {
     let input = include_str!("../inputs/day13.txt");
 
-    let res = part1(input);
-    println!("Part 1: {}", res);
-    part2(input);
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["--replay", path] => replay_session(input, Path::new(path)),
+        args => {
+            let res = part1(input);
+            println!("Part 1: {}", res);
+            let record = match args {
+                ["--record", path] => Some(Path::new(*path)),
+                _ => None,
+            };
+            part2(input, record);
+        }
+    }
 }
\ No newline at end of file
@@ -1,8 +1,6 @@
-use std::collections::{HashMap, VecDeque};
-use std::collections::hash_map::RandomState;
-use std::cmp::min;
-use std::pin::Pin;
-use std::borrow::Borrow;
+use aoc2019::bfs::Graph;
+use aoc2019::toposort::toposort;
+use std::collections::HashMap;
 
 #[derive(Debug)]
 struct ProductionRule {
@@ -30,93 +28,77 @@ fn parse(input: &str) -> Rules {
         let (n_produced, elem_produced) = parse_elem(right);
         let required = left.split(",").map(parse_elem).collect::<Vec<_>>();
 
-        (elem_produced, ProductionRule::new((n_produced), required))
+        (elem_produced, ProductionRule::new(n_produced , required))
     }).collect()
 }
 
+// Edges go from a chemical to the ones its reaction requires, with the quantity
+struct Reactions<'a>(&'a Rules);
+
+impl<'a> Graph for Reactions<'a> {
+    type Node = &'a str;
+    type Edge = u64;
+    type Adjacents = Vec<(u64, &'a str)>;
+
+    fn adjacents(&self, node: &&'a str) -> Self::Adjacents {
+        self.0.get(*node)
+            .map(|rule| rule.required.iter().map(|(n, e)| (*n, e.as_str())).collect())
+            .unwrap_or_default()
+    }
+}
+
 #[derive(Debug)]
 struct Solver<'a> {
     rules: &'a Rules,
-    required: VecDeque<(u64, &'a str)>,
-    remaining_map: HashMap<&'a str, u64>,
-    ore: u64,
+    // Every chemical comes before the ones it is made of, so all its uses are known when it is produced
+    order: Vec<&'a str>,
 }
 
 impl<'a> Solver<'a> {
     pub fn new(rules: &'a Rules) -> Self {
-        Solver {
-            rules,
-            required: VecDeque::new(),
-            remaining_map: HashMap::new(),
-            ore: 0,
-        }
-    }
-
-    pub fn require_fuel(&mut self, count: u64) {
-        self.required.push_back((count, "FUEL"));
+        let order = toposort(&Reactions(rules), Some("FUEL"))
+            .unwrap_or_else(|cycle| panic!("Reactions must not depend on themselves, {}", cycle));
+        Solver { rules, order }
     }
 
-    pub fn solve(&mut self) -> u64 {
-        while !self.required.is_empty() {
-            let (goal_qty, goal_elem) = self.required.pop_front().unwrap();
-            if goal_elem == "ORE" {
-                self.ore += goal_qty;
-            } else {
-                let mut remaining = *self.remaining_map.get(goal_elem).unwrap_or(&0);
-                if remaining > 0 {
-                    let to_use = min(remaining, goal_qty);
-                    remaining -= to_use;
-                    self.remaining_map.insert(goal_elem, remaining);
-                    self.generate_goal(goal_qty - to_use, goal_elem);
-                } else {
-                    self.generate_goal(goal_qty, &goal_elem)
+    pub fn ore_for(&self, fuel: u64) -> u64 {
+        let mut needed: HashMap<&str, u64> = HashMap::new();
+        needed.insert("FUEL", fuel);
+        for elem in &self.order {
+            if let Some(rule) = self.rules.get(*elem) {
+                let times = needed.get(elem).copied().unwrap_or(0).div_ceil(rule.produced);
+                for (n, e) in &rule.required {
+                    *needed.entry(e.as_str()).or_insert(0) += n * times;
                 }
             }
         }
-        self.ore
-    }
-
-    fn generate_goal(&mut self, goal_qty: u64, goal_elem: &'a str) {
-        let rule = &self.rules[goal_elem];
-        let mut times = goal_qty / rule.produced;
-        let has_remaining = goal_qty % rule.produced > 0;
-        if has_remaining {
-            times += 1;
-        }
-        for (n, e) in &rule.required {
-            self.required.push_back((n * times, e))
-        }
-        if has_remaining {
-            let remaining = rule.produced * times - goal_qty;
-            *self.remaining_map.entry(goal_elem).or_insert(0) += remaining;
-        }
+        needed.get("ORE").copied().unwrap_or(0)
     }
 }
 
 fn solve_part_1(rules: &HashMap<String, ProductionRule>) -> u64 {
-    let mut solver = Solver::new(rules);
-    solver.require_fuel(1);
-    solver.solve()
+    Solver::new(rules).ore_for(1)
 }
 
 fn solve_part_2(rules: &HashMap<String, ProductionRule>) -> u64 {
-    let mut fuel = 1760742;
-    let mut solver = Solver::new(rules);
-    solver.require_fuel(fuel);
-    loop {
-        let ore = solver.solve();
-        if ore <= 1000000000000 {
-            fuel += 1;
-            solver.require_fuel(1);
+    let available = 1000000000000;
+    let solver = Solver::new(rules);
+    // Enough ore for `low` fuel but not for `high`
+    let (mut low, mut high) = (0, 1);
+    while solver.ore_for(high) <= available {
+        high *= 2;
+    }
+    while high - low > 1 {
+        let mid = (low + high) / 2;
+        if solver.ore_for(mid) <= available {
+            low = mid;
         } else {
-            println!("{:?}", solver);
-            break;
+            high = mid;
         }
     }
-    fuel - 1
+    low
 }
 
-
 fn main() {
     let rules = parse(include_str!("../inputs/day14.txt"));
     println!("{:?}", rules);
@@ -1,10 +1,15 @@
-use std::collections::{BTreeMap, HashMap};
+use std::collections::BTreeMap;
+use std::env;
+use std::path::Path;
 
 use smallvec::SmallVec;
 
-use aoc2019::bfs::Graph;
+use aoc2019::bfs::{bfs_distances, Graph};
 use aoc2019::bfs_alt::bfs_alt;
+use aoc2019::bidirectional::{bidirectional_bfs, ReversibleGraph};
 use aoc2019::intcode::{Int, IntcodeCpu, parse_intcode_program};
+use aoc2019::intcode::protocol::{Decoder, Status, StatusProtocol};
+use aoc2019::intcode::session::{save_session, Recorder};
 
 struct World {
     blocks: BTreeMap<(i32, i32), Block>
@@ -27,17 +32,11 @@ enum Block {
 
 impl Block {
     fn is_path(self) -> bool {
-        match self {
-            Block::Path => true,
-            _ => false
-        }
+        matches!(self, Block::Path)
     }
 
     fn is_goal(self) -> bool {
-        match self {
-            Block::Goal => true,
-            _ => false
-        }
+        matches!(self, Block::Goal)
     }
 }
 
@@ -74,6 +73,26 @@ impl Graph for World {
     }
 }
 
+// A cell can be entered from the open cells around it, with the move back toward it
+impl ReversibleGraph for World {
+    fn predecessors(&self, node: &Self::Node) -> Self::Adjacents {
+        [Move::North, Move::South, Move::West, Move::East]
+            .iter()
+            .map(|m| (opposite(*m), apply_move(*node, *m)))
+            .filter(|(_, from)| self.blocks.get(from).is_some_and(|block| !matches!(block, Block::Wall)))
+            .collect()
+    }
+}
+
+fn opposite(m: Move) -> Move {
+    match m {
+        Move::North => Move::South,
+        Move::South => Move::North,
+        Move::West => Move::East,
+        Move::East => Move::West,
+    }
+}
+
 fn apply_move(pos: (i32, i32), m: Move) -> (i32, i32) {
     let (x, y) = pos;
     match m {
@@ -86,32 +105,34 @@ fn apply_move(pos: (i32, i32), m: Move) -> (i32, i32) {
 
 struct Drone {
     cpu: IntcodeCpu,
+    recorder: Recorder,
+    decoder: Decoder<StatusProtocol>,
     pos: (i32, i32)
 }
 
 impl Drone {
     pub fn new() -> Self {
         let source = include_str!("../inputs/day15.txt");
-        let program = parse_intcode_program(source);
+        let program = parse_intcode_program(source).expect("Invalid Intcode program");
         let cpu = IntcodeCpu::new_with_inputs_and_large_mem(64_000, program, Vec::new());
-        Drone { cpu, pos: (0, 0) }
+        Drone { cpu, recorder: Recorder::new(), decoder: Decoder::new(StatusProtocol), pos: (0, 0) }
     }
 
     fn try_move(&mut self, m: Move) -> (Block, (i32, i32)) {
         self.cpu.inputs.push_back(m as Int);
-        self.cpu.run();
+        self.recorder.run(&mut self.cpu);
         let new_pos = apply_move(self.pos, m);
-        let new_block = match self.cpu.outputs.pop().unwrap() {
-            0 => Block::Wall,
-            1 => {
+        let status = self.decoder.drain(&mut self.cpu).expect("Invalid status output").pop().unwrap();
+        let new_block = match status {
+            Status::HitWall => Block::Wall,
+            Status::Moved => {
                 self.pos = new_pos;
                 Block::Path
             }
-            2 => {
+            Status::FoundOxygen => {
                 self.pos = new_pos;
                 Block::Goal
             }
-            _ => panic!("Unreachable")
         };
         (new_block, new_pos)
     }
@@ -123,7 +144,7 @@ fn solve_part_1() {
 
     loop {
         let path_to_unknown = bfs_alt(&world, drone.pos, |g, n| {
-            g.blocks.get(n).is_none()
+            !g.blocks.contains_key(n)
         }).unwrap();
 
         for m in path_to_unknown {
@@ -137,16 +158,21 @@ fn solve_part_1() {
     }
 }
 
-fn solve_part_2() {
+fn solve_part_2(record: Option<&Path>) {
     let mut drone = Drone::new();
     let mut world = World::new();
 
     let goal = explore_all_map(&mut drone, &mut world);
     println!("Done exploring, goal = {:?}", goal);
+    if let Some(path) = record {
+        save_session(path, drone.recorder.session()).expect("Cannot save the session");
+    }
 
+    // One flood fill from the oxygen, walls are nodes of the graph too but only open cells count
+    let distances = bfs_distances(&world, goal);
     let depth = world.blocks.iter()
         .filter(|(_, block)| block.is_path())
-        .map(|(pos, _)| bfs_alt(&world, *pos, |_, n| n == &goal).unwrap().len())
+        .map(|(pos, _)| distances[pos])
         .max()
         .unwrap();
 
@@ -157,7 +183,7 @@ fn explore_all_map(drone: &mut Drone, world: &mut World) -> (i32, i32) {
     let mut goal = (0, 0);
 
     while let Some(path_to_unknown) = bfs_alt(world, drone.pos, |g, n| {
-        g.blocks.get(n).is_none()
+        !g.blocks.contains_key(n)
     }) {
         for m in path_to_unknown {
             let (new_block, new_pos) = drone.try_move(m);
@@ -172,13 +198,17 @@ fn explore_all_map(drone: &mut Drone, world: &mut World) -> (i32, i32) {
 
 fn print_path_to_objective(world: &World, goal: (i32, i32)) {
     println!("Found objective ! {:?}", goal);
-    let path = bfs_alt(world, (0, 0), |_, n| {
-        n == &goal
-    });
+    let path = bidirectional_bfs(world, (0, 0), goal);
     println!("Result : {:?}", path.unwrap().len());
 }
 
 fn main() {
+    // The exploration can be saved and replayed with `intcode --replay`
+    let args: Vec<String> = env::args().skip(1).collect();
+    let record = match args.as_slice() {
+        [flag, path] if flag == "--record" => Some(Path::new(path)),
+        _ => None,
+    };
     solve_part_1();
-    solve_part_2()
+    solve_part_2(record)
 }
\ No newline at end of file
@@ -38,7 +38,7 @@ fn fft(input: &[i32]) -> Vec<i32> {
     (1 ..= input.len()).map(|i| {
         let p = pattern(i);
         input.iter().zip(p)
-            .map(|(a, b)| (a * b))
+            .map(|(a, b)| a * b)
             .sum::<i32>().rem(10).abs()
     }).collect_vec()
 }
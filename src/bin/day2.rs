@@ -1,20 +1,27 @@
 use aoc2019::intcode::{IntcodeCpu, parse_intcode_program, Int};
+use aoc2019::intcode::batch::{BatchRunner, Job};
+use aoc2019::intcode::patch::{find_patch_set, parse_patch_sets};
+use std::sync::Arc;
 
 fn main() {
     let input = include_str!("../inputs/day2.txt");
-    solve_part1(&input);
-    solve_part2(&input);
+    solve_part1(input);
+    solve_part2(input);
 }
 
 fn solve_part1(input: &str) {
-    let memory: Vec<Int> = parse_intcode_program(input);
+    let memory: Vec<Int> = parse_intcode_program(input).expect("Invalid Intcode program");
+    let sets = parse_patch_sets(include_str!("../inputs/day2_patches.txt")).expect("Invalid patch sets");
+    let alarm = find_patch_set(&sets, "alarm_1202").expect("Missing patch set");
 
-    let res = run_with_args(&memory, 12, 2);
-    println!("Part 1: {:?}", res)
+    let mut cpu = IntcodeCpu::new(memory);
+    alarm.apply(&mut cpu).expect("Patch does not fit the program");
+    cpu.run();
+    println!("Part 1: {:?}", cpu.memory[0])
 }
 
 fn solve_part2(input: &str) {
-    let rom: Vec<Int> = parse_intcode_program(input);
+    let rom: Vec<Int> = parse_intcode_program(input).expect("Invalid Intcode program");
 
     if let Some((n, v)) = find_matching_input(&rom, 19_690_720) {
         println!("Part 2: {}", 100 * n + v)
@@ -22,9 +29,16 @@ fn solve_part2(input: &str) {
 }
 
 fn find_matching_input(rom: &[Int], out: Int) -> Option<(Int, Int)> {
-    all_inputs().find(|(noun, verb)| {
-        run_with_args(&rom, *noun, *verb) == out
-    })
+    let program: Arc<[Int]> = Arc::from(rom);
+    let jobs = all_inputs().map(|(noun, verb)| {
+        Job::new(program.clone()).patch(1, noun).patch(2, verb)
+    });
+    // Dropping the results once a match is found cancels the jobs not started yet
+    let results = BatchRunner::new().memory_size(rom.len()).run_stream(jobs);
+
+    all_inputs().zip(results)
+        .find(|(_, result)| result.cpu.memory[0] == out)
+        .map(|(args, _)| args)
 }
 
 fn all_inputs() -> impl Iterator<Item=(Int, Int)> {
@@ -32,11 +46,3 @@ fn all_inputs() -> impl Iterator<Item=(Int, Int)> {
         (noun, verb)
     }))
 }
-
-fn run_with_args(rom: &[Int], noun: Int, verb: Int) -> Int {
-    let mut cpu = IntcodeCpu::new(Vec::from(rom));
-    cpu.memory[1] = noun;
-    cpu.memory[2] = verb;
-    cpu.run();
-    cpu.memory[0]
-}
\ No newline at end of file
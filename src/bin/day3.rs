@@ -36,8 +36,8 @@ fn run_wires(input: &str) -> World {
     let mut world = World::default();
     let mut wire_a = Wire::new(Id(0));
     let mut wire_b = Wire::new(Id(1));
-    a_moves.iter().for_each(|m| wire_a.apply_move(&mut world, &m));
-    b_moves.iter().for_each(|m| wire_b.apply_move(&mut world, &m));
+    a_moves.iter().for_each(|m| wire_a.apply_move(&mut world, m));
+    b_moves.iter().for_each(|m| wire_b.apply_move(&mut world, m));
     world
 }
 
@@ -124,7 +124,7 @@ fn parse_all_moves(input: &str) -> (Vec<Move>, Vec<Move>) {
 
 fn parse_moves(input: &str) -> Vec<Move> {
     input.split(',').map(|m| {
-        let dir = &m.chars().nth(0).unwrap();
+        let dir = &m.chars().next().unwrap();
         let n: MoveInt = m[1..].parse().unwrap();
 
         match dir {
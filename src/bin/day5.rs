@@ -2,7 +2,7 @@ use aoc2019::intcode::{parse_intcode_program, run_with_inputs};
 
 fn main() {
     let input = include_str!("../inputs/day5.txt");
-    let memory = parse_intcode_program(input);
+    let memory = parse_intcode_program(input).expect("Invalid Intcode program");
 
     let out = run_with_inputs(memory.clone(), vec![1]);
     println!("Part 1 : {:?}", out.last().expect("No diagnostic code"));
@@ -1,4 +1,7 @@
-use aoc2019::bfs::{Graph, bfs};
+use aoc2019::bfs::Graph;
+use aoc2019::toposort::toposort;
+use aoc2019::traversal::Bfs;
+use aoc2019::tree::Tree;
 use std::collections::HashMap;
 
 fn main() {
@@ -29,25 +32,31 @@ fn parse_transitions(input: &str) -> Vec<(String, String)> {
 fn solve_part1(input: &str) -> usize {
     let transitions = parse_transitions(input);
     let graph = orbit_graph_from_transitions(transitions);
-    let (explorer, _) = bfs(&&graph, &graph.initial, |_| false);
-    explorer.cache.iter().fold(0, |acc, n| acc + n.0.depth)
+    validate_orbits(&graph);
+    Bfs::new(&&graph, &graph.initial).map(|(_, depth, _)| depth).sum()
 }
 
-
-fn solve_part2(input: &str) -> usize {
-    let transitions = add_reversed_transitions(parse_transitions(input));
-    let mut graph = orbit_graph_from_transitions(transitions);
-    graph.initial = String::from("YOU");
-    let (_, path) = bfs(&&graph, &graph.initial, |n| *n == "SAN");
-
-    path.expect("A path was not found").len() - 2
+// Counting orbits by depth needs every object to orbit exactly one other, down to COM
+fn validate_orbits(graph: &OrbitGraph) {
+    let order = toposort(&graph, Some(graph.initial.as_str()))
+        .unwrap_or_else(|cycle| panic!("Orbits must not loop, {}", cycle));
+    let orbiting: usize = graph.orbits.values().map(|objects| objects.len()).sum();
+    let mut objects: Vec<&str> = graph.orbits.values().flatten().map(|o| o.as_str()).collect();
+    objects.sort_unstable();
+    objects.dedup();
+    assert_eq!(orbiting, objects.len(), "An object orbits several others");
+    assert_eq!(order.len(), objects.len() + 1, "Some objects are not orbiting COM");
 }
 
-fn add_reversed_transitions(transitions: Vec<(String, String)>) -> Vec<(String, String)> {
-    let mut transitions = transitions;
-    let mut reversed = transitions.iter().map(|(a, b)| (b.clone(), a.clone())).collect();
-    transitions.append(&mut reversed);
-    transitions
+fn solve_part2(input: &str) -> usize {
+    let tree = Tree::from_pairs(parse_transitions(input)).unwrap_or_else(|e| panic!("Invalid orbits, {}", e));
+    let you = String::from("YOU");
+    let santa = String::from("SAN");
+
+    // Between the objects YOU and SAN are orbiting
+    let you = tree.parent(&you).expect("YOU is missing or orbits nothing");
+    let santa = tree.parent(&santa).expect("SAN is missing or orbits nothing");
+    tree.distance(you, santa).expect("The parents are in the tree")
 }
 
 #[derive(Debug)]
@@ -70,7 +79,7 @@ impl<'a> Graph for &'a OrbitGraph {
     fn adjacents(&self, node: &Self::Node) -> Self::Adjacents {
         self.orbits.get(*node).map(|adj| adj.iter().map(|e| ((), e.as_str()))
             .collect())
-            .unwrap_or(Vec::new())
+            .unwrap_or_default()
     }
 }
 
@@ -111,4 +120,13 @@ K)YOU
 I)SAN");
         assert_eq!(res, 4);
     }
+
+    #[test]
+    fn test_part_2_ancestor() {
+        // From COM to A
+        let res = solve_part2("COM)YOU
+YOU)A
+A)SAN");
+        assert_eq!(res, 2);
+    }
 }
\ No newline at end of file
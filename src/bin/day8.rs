@@ -31,14 +31,14 @@ fn parse_layers(input: &str, width: u8, height: u8) -> Vec<Layer> {
     layers
 }
 
-fn solve_part_1(layers: &Vec<Layer>) -> usize {
+fn solve_part_1(layers: &[Layer]) -> usize {
     let layer = layers.iter()
         .min_by_key(|l| l.number_of_digit(0))
         .unwrap();
     layer.number_of_digit(1) * layer.number_of_digit(2)
 }
 
-fn solve_part_2(layers: &Vec<Layer>) -> String {
+fn solve_part_2(layers: &[Layer]) -> String {
     let mut image = [[" "; 25]; 6];
 
     for row in 0 .. 6 {
@@ -51,7 +51,7 @@ fn solve_part_2(layers: &Vec<Layer>) -> String {
     image.iter().map(|l| l.join("")).join("\n")
 }
 
-fn color_at(layers: &Vec<Layer>, row: u8, col: u8) -> u8 {
+fn color_at(layers: &[Layer], row: u8, col: u8) -> u8 {
     layers.iter()
         .map(|l| l.value_at(row, col))
         .find(|p| *p != 2)
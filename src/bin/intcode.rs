@@ -0,0 +1,420 @@
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+use std::process;
+
+use aoc2019::intcode::{Int, IntcodeCpu, StopReason, catch_panic};
+use aoc2019::intcode::binary::{parse_image, BinaryImage};
+use aoc2019::intcode::coverage::Coverage;
+use aoc2019::intcode::session::{load_session, replay};
+use aoc2019::intcode::self_modify::analyze;
+
+const USAGE: &str = "Usage: intcode [OPTIONS] [PROGRAM]
+
+Runs an Intcode program read from PROGRAM, or from stdin when missing or \"-\".
+PROGRAM is either comma separated text or a binary image.
+
+Options:
+  -i, --input <VALUES>      comma separated inputs, a line of text in ASCII mode (repeatable)
+      --input-file <PATH>   read inputs from a file
+      --ascii               inputs are text, outputs below 128 are printed as characters
+      --memory-size <N>     memory size in cells [default: 65536 or the size the image requires]
+      --auto-grow           grow memory on demand instead of using a fixed size
+      --max-steps <N>       stop after executing N instructions
+      --dump-memory         print the final memory on exit
+      --self-modifying      report writes to executed instructions on stderr
+      --format <FORMAT>     output format, lines or json [default: lines]
+      --replay <PATH>       feed the inputs of a recorded session instead of --input and check
+                            the outputs that followed each of them
+      --coverage            print the disassembly with instruction hit counts on stderr
+      --symbols             print the sections and symbols of the program on stderr
+      --write-image <PATH>  convert the program to a binary image instead of running it
+      --write-text <PATH>   convert the program to text instead of running it
+  -h, --help                print this help
+
+Exit codes:
+  0   the program halted
+  1   the interpreter hit an error
+  2   the program is blocked waiting for input
+  3   the step limit was reached
+  64  invalid arguments or unreadable files";
+
+const EXIT_HALTED: i32 = 0;
+const EXIT_ERROR: i32 = 1;
+const EXIT_WAITING_FOR_INPUT: i32 = 2;
+const EXIT_OUT_OF_BUDGET: i32 = 3;
+const EXIT_USAGE: i32 = 64;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Format {
+    Lines,
+    Json,
+}
+
+#[derive(Debug)]
+struct Options {
+    program_path: Option<String>,
+    inputs: Vec<String>,
+    input_file: Option<String>,
+    ascii: bool,
+    memory_size: Option<usize>,
+    auto_grow: bool,
+    max_steps: Option<usize>,
+    dump_memory: bool,
+    self_modifying: bool,
+    format: Format,
+    replay: Option<String>,
+    coverage: bool,
+    symbols: bool,
+    write_image: Option<String>,
+    write_text: Option<String>,
+}
+
+const DEFAULT_MEMORY_SIZE: usize = 64 * 1024;
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            program_path: None,
+            inputs: Vec::new(),
+            input_file: None,
+            ascii: false,
+            memory_size: None,
+            auto_grow: false,
+            max_steps: None,
+            dump_memory: false,
+            self_modifying: false,
+            format: Format::Lines,
+            replay: None,
+            coverage: false,
+            symbols: false,
+            write_image: None,
+            write_text: None,
+        }
+    }
+}
+
+fn parse_args<I>(args: I) -> Result<Options, String>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut options = Options::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or(format!("missing value for {}", name));
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(EXIT_HALTED);
+            }
+            "-i" | "--input" => options.inputs.push(value(&arg)?),
+            "--input-file" => options.input_file = Some(value(&arg)?),
+            "--ascii" => options.ascii = true,
+            "--memory-size" => options.memory_size = Some(parse_number(&arg, &value(&arg)?)?),
+            "--auto-grow" => options.auto_grow = true,
+            "--max-steps" => options.max_steps = Some(parse_number(&arg, &value(&arg)?)?),
+            "--dump-memory" => options.dump_memory = true,
+            "--self-modifying" => options.self_modifying = true,
+            "--format" => {
+                options.format = match value(&arg)?.as_str() {
+                    "lines" => Format::Lines,
+                    "json" => Format::Json,
+                    other => return Err(format!("unknown format {}", other)),
+                }
+            }
+            "--replay" => options.replay = Some(value(&arg)?),
+            "--coverage" => options.coverage = true,
+            "--symbols" => options.symbols = true,
+            "--write-image" => options.write_image = Some(value(&arg)?),
+            "--write-text" => options.write_text = Some(value(&arg)?),
+            _ if arg.starts_with('-') && arg != "-" => return Err(format!("unknown option {}", arg)),
+            _ if options.program_path.is_none() => options.program_path = Some(arg),
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+    Ok(options)
+}
+
+fn parse_number(name: &str, value: &str) -> Result<usize, String> {
+    value.parse().map_err(|_| format!("invalid value {} for {}", value, name))
+}
+
+fn parse_values(source: &str) -> Result<Vec<Int>, String> {
+    source.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|v| !v.is_empty())
+        .map(|v| v.parse().map_err(|_| format!("invalid input value {}", v)))
+        .collect()
+}
+
+fn ascii_values(text: &str) -> Vec<Int> {
+    text.bytes().map(Int::from).collect()
+}
+
+fn read_source(path: Option<&str>) -> io::Result<Vec<u8>> {
+    match path {
+        None | Some("-") => {
+            let mut source = Vec::new();
+            io::stdin().read_to_end(&mut source)?;
+            Ok(source)
+        }
+        Some(path) => fs::read(path),
+    }
+}
+
+fn collect_inputs(options: &Options) -> Result<Vec<Int>, String> {
+    let mut inputs = Vec::new();
+    for input in &options.inputs {
+        if options.ascii {
+            inputs.extend(ascii_values(input));
+            inputs.push('\n' as Int);
+        } else {
+            inputs.extend(parse_values(input)?);
+        }
+    }
+    if let Some(path) = &options.input_file {
+        let content = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+        if options.ascii {
+            inputs.extend(ascii_values(&content));
+        } else {
+            inputs.extend(parse_values(&content)?);
+        }
+    }
+    Ok(inputs)
+}
+
+fn load_program(options: &Options) -> Result<BinaryImage, String> {
+    let path = options.program_path.as_deref();
+    let source = read_source(path)
+        .map_err(|e| format!("cannot read {}: {}", path.unwrap_or("stdin"), e))?;
+    parse_image(&source).map_err(|e| format!("invalid program {}: {}", path.unwrap_or("stdin"), e))
+}
+
+fn build_cpu(image: &BinaryImage, options: &Options) -> Result<IntcodeCpu, String> {
+    let inputs = collect_inputs(options)?;
+    let size = match options.memory_size {
+        _ if options.auto_grow => image.memory_size,
+        Some(size) => size,
+        None => DEFAULT_MEMORY_SIZE.max(image.memory_size),
+    };
+    if image.memory_size > size {
+        return Err(format!("program requires {} cells, memory has {} cells", image.memory_size, size));
+    }
+    let mut cpu = image.cpu(size, inputs);
+    cpu.memory.set_auto_grow(options.auto_grow);
+    Ok(cpu)
+}
+
+fn write_image(image: &BinaryImage, options: &Options) -> Result<(), String> {
+    let write = |path: &str, content: &[u8]| fs::write(path, content).map_err(|e| format!("cannot write {}: {}", path, e));
+    if let Some(path) = &options.write_image {
+        write(path, &image.encode())?;
+    }
+    if let Some(path) = &options.write_text {
+        write(path, image.to_text().as_bytes())?;
+    }
+    Ok(())
+}
+
+fn print_symbols(image: &BinaryImage) {
+    for section in &image.sections {
+        eprintln!("section {} {}..{}", section.name, section.start, section.start + section.len);
+    }
+    for (name, addr) in &image.symbols {
+        eprintln!("symbol {} {}", name, addr);
+    }
+}
+
+// `name+offset` of the closest symbol
+fn location(image: &BinaryImage, pc: usize) -> String {
+    match image.locate(pc) {
+        Some((name, 0)) => format!(" ({})", name),
+        Some((name, offset)) => format!(" ({}+{})", name, offset),
+        None => String::new(),
+    }
+}
+
+enum Outcome {
+    Stopped(StopReason),
+    Error(String),
+}
+
+impl Outcome {
+    fn name(&self) -> &'static str {
+        match self {
+            Outcome::Stopped(StopReason::Halted) => "halted",
+            Outcome::Stopped(StopReason::WaitingForInput) => "waiting_for_input",
+            Outcome::Stopped(StopReason::OutOfBudget) => "out_of_budget",
+            Outcome::Error(_) => "error",
+        }
+    }
+
+    fn exit_code(&self) -> i32 {
+        match self {
+            Outcome::Stopped(StopReason::Halted) => EXIT_HALTED,
+            Outcome::Stopped(StopReason::WaitingForInput) => EXIT_WAITING_FOR_INPUT,
+            Outcome::Stopped(StopReason::OutOfBudget) => EXIT_OUT_OF_BUDGET,
+            Outcome::Error(_) => EXIT_ERROR,
+        }
+    }
+}
+
+fn run(cpu: &mut IntcodeCpu, max_steps: Option<usize>, coverage: Option<&mut Coverage>) -> Outcome {
+    let budget = max_steps.unwrap_or(usize::MAX);
+    let result = match coverage {
+        Some(coverage) => catch_panic(|| coverage.record(cpu, budget)),
+        None => catch_panic(|| cpu.run_with_budget(budget)),
+    };
+    match result {
+        Ok(reason) => Outcome::Stopped(reason),
+        Err(message) => Outcome::Error(message),
+    }
+}
+
+fn memory_dump(cpu: &IntcodeCpu) -> Vec<Int> {
+    let mut memory = cpu.memory.to_vec();
+    while memory.last() == Some(&0) {
+        memory.pop();
+    }
+    memory
+}
+
+fn join(values: &[Int]) -> String {
+    values.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(",")
+}
+
+fn json_string(s: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+fn print_lines(cpu: &IntcodeCpu, outcome: &Outcome, options: &Options, image: &BinaryImage) {
+    if options.ascii {
+        let mut text = String::new();
+        for v in &cpu.outputs {
+            match *v {
+                0..=127 => text.push(*v as u8 as char),
+                _ => text.push_str(&format!("{}\n", v)),
+            }
+        }
+        print!("{}", text);
+    } else {
+        for v in &cpu.outputs {
+            println!("{}", v);
+        }
+    }
+    if options.dump_memory {
+        eprintln!("{}", join(&memory_dump(cpu)));
+    }
+    match outcome {
+        Outcome::Error(message) => eprintln!("error at pc {}{}: {}", cpu.pc, location(image, cpu.pc), message),
+        Outcome::Stopped(StopReason::Halted) => {}
+        _ => eprintln!("stopped: {} after {} steps", outcome.name(), cpu.steps),
+    }
+}
+
+fn print_json(cpu: &IntcodeCpu, outcome: &Outcome, options: &Options) {
+    let mut fields = vec![
+        format!("\"status\":{}", json_string(outcome.name())),
+        format!("\"pc\":{}", cpu.pc),
+        format!("\"steps\":{}", cpu.steps),
+        format!("\"outputs\":[{}]", join(&cpu.outputs)),
+    ];
+    if let Outcome::Error(message) = outcome {
+        fields.push(format!("\"error\":{}", json_string(message)));
+    }
+    if options.dump_memory {
+        fields.push(format!("\"memory\":[{}]", join(&memory_dump(cpu))));
+    }
+    println!("{{{}}}", fields.join(","));
+}
+
+fn main() {
+    let options = parse_args(env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("error: {}\n\n{}", e, USAGE);
+        process::exit(EXIT_USAGE)
+    });
+    let fail = |e: String| -> ! {
+        eprintln!("error: {}", e);
+        process::exit(EXIT_USAGE)
+    };
+    let image = load_program(&options).unwrap_or_else(|e| fail(e));
+    if options.symbols {
+        print_symbols(&image);
+    }
+    if options.write_image.is_some() || options.write_text.is_some() {
+        write_image(&image, &options).unwrap_or_else(|e| fail(e));
+        process::exit(EXIT_HALTED);
+    }
+    let mut cpu = build_cpu(&image, &options).unwrap_or_else(|e| fail(e));
+
+    if options.self_modifying {
+        let report = catch_panic(|| analyze(&cpu, options.max_steps.unwrap_or(usize::MAX)));
+        for modification in report.iter().flat_map(|report| &report.modifications) {
+            eprintln!("self-modifying: {}", modification);
+        }
+    }
+
+    if let Some(path) = &options.replay {
+        let session = load_session(Path::new(path)).unwrap_or_else(|e| fail(e));
+        cpu.inputs.clear();
+        match replay(cpu.fork(), &session, options.max_steps.unwrap_or(usize::MAX)) {
+            Ok(replayed) => cpu = replayed,
+            Err(divergence) => {
+                eprintln!("replay diverged: {}", divergence);
+                process::exit(EXIT_ERROR);
+            }
+        }
+    }
+
+    let mut coverage = Coverage::new();
+    // The steps spent replaying count against the budget
+    let max_steps = options.max_steps.map(|max| max.saturating_sub(cpu.steps));
+    let outcome = run(&mut cpu, max_steps, Some(&mut coverage).filter(|_| options.coverage));
+    if options.coverage {
+        eprint!("{}", coverage.report(&image));
+    }
+    match options.format {
+        Format::Lines => print_lines(&cpu, &outcome, &options, &image),
+        Format::Json => print_json(&cpu, &outcome, &options),
+    }
+    process::exit(outcome.exit_code());
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Format, parse_args, parse_values};
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let options = parse_args(args("-i 1,2 --input 3 --max-steps 10 --format json prog.txt")).unwrap();
+        assert_eq!(options.inputs, vec!["1,2", "3"]);
+        assert_eq!(options.max_steps, Some(10));
+        assert_eq!(options.format, Format::Json);
+        assert_eq!(options.program_path.as_deref(), Some("prog.txt"));
+
+        assert!(parse_args(args("--max-steps")).is_err());
+        assert!(parse_args(args("--format xml")).is_err());
+        assert!(parse_args(args("a.txt b.txt")).is_err());
+    }
+
+    #[test]
+    fn test_parse_values() {
+        assert_eq!(parse_values("1, 2\n-3 4\n"), Ok(vec![1, 2, -3, 4]));
+        assert!(parse_values("1,x").is_err());
+    }
+}
@@ -0,0 +1,122 @@
+use std::env;
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use aoc2019::intcode::fuzz::{fuzz, Summary};
+use aoc2019::intcode::Int;
+
+const USAGE: &str = "Usage: intcode_fuzz [OPTIONS]
+
+Compares the Intcode interpreter with a reference interpreter on random programs,
+until a divergence is found or the iterations are exhausted.
+
+Options:
+      --seed <N>         first seed [default: current time]
+      --iterations <N>   number of programs to run [default: unlimited]
+      --max-steps <N>    step budget of each program [default: 1000]
+  -h, --help             print this help";
+
+const BATCH: usize = 10_000;
+
+#[derive(Debug)]
+struct Options {
+    seed: u64,
+    iterations: Option<usize>,
+    max_steps: usize,
+}
+
+fn parse_args<I>(args: I, default_seed: u64) -> Result<Options, String>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut options = Options { seed: default_seed, iterations: None, max_steps: 1000 };
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        // Only read by the options taking a value, so flags do not swallow the next argument
+        let mut value = || args.next().ok_or(format!("missing value for {}", arg));
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            "--seed" => options.seed = parse_number(&arg, &value()?)?,
+            "--iterations" => options.iterations = Some(parse_number(&arg, &value()?)?),
+            "--max-steps" => options.max_steps = parse_number(&arg, &value()?)?,
+            _ => return Err(format!("unknown option {}", arg)),
+        }
+    }
+    Ok(options)
+}
+
+fn parse_number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid value {} for {}", value, name))
+}
+
+fn join(values: &[Int]) -> String {
+    values.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(",")
+}
+
+fn main() {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let options = parse_args(env::args().skip(1), now).unwrap_or_else(|e| {
+        eprintln!("error: {}\n\n{}", e, USAGE);
+        process::exit(64)
+    });
+
+    // Each batch uses its own seed, so a divergence can be reproduced with --seed alone
+    let mut total = Summary::default();
+    let mut seed = options.seed;
+    let mut remaining = options.iterations;
+    while remaining != Some(0) {
+        let batch = remaining.map(|r| r.min(BATCH)).unwrap_or(BATCH);
+        match fuzz(seed, batch, options.max_steps) {
+            Ok(summary) => {
+                total.cases += summary.cases;
+                total.halted += summary.halted;
+                total.waiting_for_input += summary.waiting_for_input;
+                total.out_of_budget += summary.out_of_budget;
+                total.errors += summary.errors;
+                println!("seed {}: {} cases ({} halted, {} waiting for input, {} out of budget, {} errors)",
+                         seed, total.cases, total.halted, total.waiting_for_input, total.out_of_budget, total.errors);
+            }
+            Err(divergence) => {
+                println!("seed {}: divergence", seed);
+                println!("program: {}", join(&divergence.case.program));
+                println!("inputs: {}", join(&divergence.case.inputs));
+                println!("memory size: {}", divergence.case.memory_size);
+                println!("reference: {:?} outputs {:?} after {} steps",
+                         divergence.expected.halt, divergence.expected.outputs, divergence.expected.steps);
+                println!("cpu:       {:?} outputs {:?} after {} steps",
+                         divergence.actual.halt, divergence.actual.outputs, divergence.actual.steps);
+                let minimized = divergence.minimize(options.max_steps);
+                println!("minimized ({} tests): {}", minimized.tests, join(&minimized.program));
+                process::exit(1);
+            }
+        }
+        remaining = remaining.map(|r| r - batch);
+        seed = seed.wrapping_add(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse_args;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let options = parse_args(args("--seed 42 --iterations 100"), 0).unwrap();
+        assert_eq!(options.seed, 42);
+        assert_eq!(options.iterations, Some(100));
+        assert_eq!(options.max_steps, 1000);
+
+        assert!(parse_args(args("--seed"), 0).is_err());
+        assert!(parse_args(args("--seed x"), 0).is_err());
+        assert_eq!(parse_args(args("--verbose"), 0).unwrap_err(), "unknown option --verbose");
+        assert_eq!(parse_args(args("--verbose --seed 1"), 0).unwrap_err(), "unknown option --verbose");
+    }
+}
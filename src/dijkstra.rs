@@ -0,0 +1,115 @@
+use crate::arena::Arena;
+use crate::bfs::{Graph, Path};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::hash::Hash;
+use std::ops::Add;
+
+// Costs on top of an existing graph, `Cost::default()` is the cost of the empty path
+pub trait WeightedGraph: Graph {
+    type Cost: Copy + Ord + Default + Add<Output = Self::Cost>;
+
+    fn cost(&self, from: &Self::Node, edge: &Self::Edge, to: &Self::Node) -> Self::Cost;
+}
+
+#[derive(Debug)]
+pub struct WeightedExplorer<G: WeightedGraph>
+where
+    G::Node: Eq + Hash,
+{
+    // Lowest cost from the start of every node settled before the search stopped
+    pub cache: HashMap<G::Node, G::Cost>,
+}
+
+pub type WeightedPath<G> = (Path<G>, <G as WeightedGraph>::Cost);
+
+pub fn dijkstra<G, F>(graph: &G, initial_node: G::Node, is_done: F)
+    -> (WeightedExplorer<G>, Option<WeightedPath<G>>)
+where
+    G: WeightedGraph,
+    G::Node: Eq + Hash + Clone,
+    G::Edge: Clone,
+    F: Fn(&G::Node) -> bool,
+{
+    let mut explorer = WeightedExplorer { cache: HashMap::new() };
+    let mut best: HashMap<G::Node, G::Cost> = HashMap::new();
+    // Every node pushed on the heap
+    let mut arena: Arena<G::Node, G::Edge> = Arena::new();
+    // Ties go to the node pushed first, the index in `arena`
+    let mut heap: BinaryHeap<Reverse<(G::Cost, usize)>> = BinaryHeap::new();
+
+    best.insert(initial_node.clone(), G::Cost::default());
+    let start = arena.push(initial_node, None);
+    heap.push(Reverse((G::Cost::default(), start)));
+
+    while let Some(Reverse((cost, index))) = heap.pop() {
+        let node = arena[index].node.clone();
+        if explorer.cache.contains_key(&node) || best.get(&node) != Some(&cost) {
+            continue;
+        }
+        explorer.cache.insert(node.clone(), cost);
+        if is_done(&node) {
+            return (explorer, Some((arena.path_to(index), cost)));
+        }
+        for (edge, next_node) in graph.adjacents(&node) {
+            if explorer.cache.contains_key(&next_node) {
+                continue;
+            }
+            let next_cost = cost + graph.cost(&node, &edge, &next_node);
+            if best.get(&next_node).is_none_or(|c| next_cost < *c) {
+                best.insert(next_node.clone(), next_cost);
+                let next_index = arena.push(next_node, Some((edge, index)));
+                heap.push(Reverse((next_cost, next_index)));
+            }
+        }
+    }
+    (explorer, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bfs::{bfs, Graph};
+    use crate::dijkstra::{dijkstra, WeightedGraph};
+
+    // A row of cells, walking costs 1 per cell, jumping 2 cells ahead costs `jump`
+    struct Row {
+        len: i32,
+        jump: u32,
+    }
+
+    impl Graph for Row {
+        type Node = i32;
+        type Edge = char;
+        type Adjacents = Vec<(char, i32)>;
+
+        fn adjacents(&self, node: &i32) -> Vec<(char, i32)> {
+            vec![('w', node + 1), ('j', node + 2)].into_iter().filter(|(_, n)| *n < self.len).collect()
+        }
+    }
+
+    impl WeightedGraph for Row {
+        type Cost = u32;
+
+        fn cost(&self, _: &i32, edge: &char, _: &i32) -> u32 {
+            if *edge == 'j' { self.jump } else { 1 }
+        }
+    }
+
+    #[test]
+    fn test_dijkstra() {
+        let row = Row { len: 10, jump: 3 };
+        let (explorer, result) = dijkstra(&row, 0, |n| *n == 4);
+        let (path, cost) = result.unwrap();
+        assert_eq!(cost, 4);
+        assert_eq!(path, vec![('w', 1), ('w', 2), ('w', 3), ('w', 4)]);
+        assert_eq!(explorer.cache[&2], 2);
+
+        // Same hops as bfs when jumping is as cheap as walking
+        let row = Row { len: 10, jump: 1 };
+        let (_, result) = dijkstra(&row, 0, |n| *n == 9);
+        let (_, path) = bfs(&row, 0, |n| *n == 9);
+        assert_eq!(result.unwrap().0.len(), path.unwrap().len());
+
+        assert!(dijkstra(&row, 0, |n| *n == 10).1.is_none());
+    }
+}
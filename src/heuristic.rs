@@ -0,0 +1,161 @@
+use crate::arena::Arena;
+use crate::bfs::Graph;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+use std::hash::Hash;
+
+// Whether a path is known to have the fewest edges or was only found by following the score
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Guarantee {
+    Optimal,
+    Heuristic,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Found<Edge> {
+    pub path: Vec<Edge>,
+    pub guarantee: Guarantee,
+}
+
+// Breadth first, but only the `width` nodes with the lowest score of each level are expanded, the
+// first discovered on ties. The goal is looked for among all the nodes of a level before pruning,
+// so the path is optimal as long as no level had to be pruned. Gives up when the beam is empty.
+pub fn beam_search<G, F, S, K>(graph: &G, start: G::Node, mut is_goal: F, score: S, width: usize)
+    -> Option<Found<G::Edge>>
+where
+    G: Graph,
+    G::Node: Eq + Hash + Clone,
+    G::Edge: Clone,
+    F: FnMut(&G::Node) -> bool,
+    S: Fn(&G::Node) -> K,
+    K: Ord,
+{
+    assert!(width > 0, "The beam must keep at least one node");
+    if is_goal(&start) {
+        return Some(Found { path: Vec::new(), guarantee: Guarantee::Optimal });
+    }
+    let mut visited: HashSet<G::Node> = HashSet::new();
+    let mut arena: Arena<G::Node, G::Edge> = Arena::new();
+    let mut pruned = false;
+
+    visited.insert(start.clone());
+    let mut beam = vec![arena.push(start, None)];
+
+    while !beam.is_empty() {
+        let mut level: Vec<usize> = Vec::new();
+        for index in beam {
+            for (edge, node) in graph.adjacents(&arena[index].node) {
+                if !visited.insert(node.clone()) {
+                    continue;
+                }
+                let goal = is_goal(&node);
+                let next_index = arena.push(node, Some((edge, index)));
+                if goal {
+                    let guarantee = if pruned { Guarantee::Heuristic } else { Guarantee::Optimal };
+                    return Some(Found { path: arena.edges_to(next_index), guarantee });
+                }
+                level.push(next_index);
+            }
+        }
+        if level.len() > width {
+            // Stable, the order of discovery breaks the ties
+            level.sort_by_cached_key(|index| score(&arena[*index].node));
+            level.truncate(width);
+            pruned = true;
+        }
+        beam = level;
+    }
+    None
+}
+
+// Always expands the discovered node with the lowest score, the last discovered on ties so equal
+// scores are followed depth first. It does not stop before every reachable node was visited, but
+// the path found is only as short as the score makes it.
+pub fn best_first<G, F, S, K>(graph: &G, start: G::Node, mut is_goal: F, score: S) -> Option<Found<G::Edge>>
+where
+    G: Graph,
+    G::Node: Eq + Hash + Clone,
+    G::Edge: Clone,
+    F: FnMut(&G::Node) -> bool,
+    S: Fn(&G::Node) -> K,
+    K: Ord,
+{
+    let mut visited: HashSet<G::Node> = HashSet::new();
+    let mut arena: Arena<G::Node, G::Edge> = Arena::new();
+    let mut frontier: BinaryHeap<(Reverse<K>, usize)> = BinaryHeap::new();
+
+    visited.insert(start.clone());
+    let start_score = score(&start);
+    frontier.push((Reverse(start_score), arena.push(start, None)));
+
+    while let Some((_, index)) = frontier.pop() {
+        if is_goal(&arena[index].node) {
+            let guarantee = if index == 0 { Guarantee::Optimal } else { Guarantee::Heuristic };
+            return Some(Found { path: arena.edges_to(index), guarantee });
+        }
+        for (edge, node) in graph.adjacents(&arena[index].node) {
+            if !visited.insert(node.clone()) {
+                continue;
+            }
+            let node_score = score(&node);
+            frontier.push((Reverse(node_score), arena.push(node, Some((edge, index)))));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bfs::Graph;
+    use crate::bfs_alt::bfs_alt;
+    use crate::heuristic::{beam_search, best_first, Guarantee};
+
+    // Numbers reachable from a start by adding 1 or doubling, up to 1000
+    struct Numbers;
+
+    impl Graph for Numbers {
+        type Node = u32;
+        type Edge = char;
+        type Adjacents = Vec<(char, u32)>;
+
+        fn adjacents(&self, node: &u32) -> Vec<(char, u32)> {
+            vec![('+', node + 1), ('*', node * 2)].into_iter().filter(|(_, n)| *n <= 1000).collect()
+        }
+    }
+
+    fn distance(goal: u32) -> impl Fn(&u32) -> u32 {
+        move |n| if *n > goal { n - goal } else { goal - n }
+    }
+
+    #[test]
+    fn test_beam_search() {
+        let shortest = bfs_alt(&Numbers, 1, |_, n| *n == 20).unwrap();
+
+        // Wide enough to never prune
+        let found = beam_search(&Numbers, 1, |n| *n == 20, distance(20), 1000).unwrap();
+        assert_eq!(found.path.len(), shortest.len());
+        assert_eq!(found.guarantee, Guarantee::Optimal);
+
+        let found = beam_search(&Numbers, 1, |n| *n == 20, distance(20), 2).unwrap();
+        assert_eq!(found.guarantee, Guarantee::Heuristic);
+        assert!(found.path.len() >= shortest.len());
+
+        let found = beam_search(&Numbers, 7, |n| *n == 7, distance(7), 1).unwrap();
+        assert_eq!((found.path.len(), found.guarantee), (0, Guarantee::Optimal));
+
+        // Past the largest number of the graph
+        assert_eq!(beam_search(&Numbers, 1, |n| *n == 1001, |n| u32::MAX - n, 1), None);
+    }
+
+    #[test]
+    fn test_best_first() {
+        let found = best_first(&Numbers, 1, |n| *n == 20, distance(20)).unwrap();
+        assert_eq!(found.guarantee, Guarantee::Heuristic);
+        let end = found.path.iter().fold(1, |n, edge| if *edge == '+' { n + 1 } else { n * 2 });
+        assert_eq!(end, 20);
+
+        // Keeps going when the score leads nowhere
+        assert!(best_first(&Numbers, 1, |n| *n == 999, |n| u32::MAX - n).is_some());
+        assert_eq!(best_first(&Numbers, 1, |n| *n == 1001, distance(1001)), None);
+    }
+}
@@ -0,0 +1,283 @@
+use crate::intcode::instruction::Int;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AsmError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl Display for AsmError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for AsmError {}
+
+// Relocatable output of the assembler, addresses are relative to the start of the module
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Module {
+    pub name: String,
+    pub code: Vec<Int>,
+    // Exported labels, the ones starting with a dot stay local to the module
+    pub symbols: BTreeMap<String, usize>,
+    // Cells holding an address inside the module, shifted when the module is moved
+    pub relocations: Vec<usize>,
+    // Cells to which the address of a symbol defined by another module is added
+    pub externals: Vec<(usize, String)>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Value {
+    Const(Int),
+    // Module relative address
+    Address(usize),
+    Symbol(String, Int),
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Operand {
+    Position(Value),
+    Immediate(Value),
+    Relative(Int),
+}
+
+fn opcode(mnemonic: &str) -> Option<(Int, usize, usize)> {
+    // opcode, number of inputs, number of outputs
+    match mnemonic {
+        "add" => Some((1, 2, 1)),
+        "mul" => Some((2, 2, 1)),
+        "in" => Some((3, 0, 1)),
+        "out" => Some((4, 1, 0)),
+        "jnz" => Some((5, 2, 0)),
+        "jz" => Some((6, 2, 0)),
+        "lt" => Some((7, 2, 1)),
+        "eq" => Some((8, 2, 1)),
+        "arb" => Some((9, 1, 0)),
+        "halt" => Some((99, 0, 0)),
+        _ => None,
+    }
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_' || c == '.')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+fn parse_value(s: &str) -> Result<Value, String> {
+    let s = s.trim();
+    if let Ok(v) = s.parse() {
+        return Ok(Value::Const(v));
+    }
+    let (name, addend) = match s.find(['+', '-']) {
+        Some(i) => {
+            let addend: Int = s[i + 1..].trim().parse().map_err(|_| format!("invalid offset in {}", s))?;
+            (s[..i].trim(), if &s[i..i + 1] == "-" { -addend } else { addend })
+        }
+        None => (s, 0),
+    };
+    if is_identifier(name) {
+        Ok(Value::Symbol(name.to_owned(), addend))
+    } else {
+        Err(format!("invalid value {}", s))
+    }
+}
+
+// `rb[n]` relative, `[value]` position, `value` immediate
+fn parse_operand(s: &str) -> Result<Operand, String> {
+    let s = s.trim();
+    if let Some(offset) = s.strip_prefix("rb[").and_then(|s| s.strip_suffix(']')) {
+        let offset = offset.trim().parse().map_err(|_| format!("invalid relative offset {}", offset))?;
+        Ok(Operand::Relative(offset))
+    } else if let Some(addr) = s.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+        parse_value(addr).map(Operand::Position)
+    } else {
+        parse_value(s).map(Operand::Immediate)
+    }
+}
+
+fn encode(mnemonic: &str, operands: &[Operand], cells: &mut Vec<Value>) -> Result<(), String> {
+    let (opcode, inputs, outputs) = opcode(mnemonic).ok_or(format!("unknown instruction {}", mnemonic))?;
+    if operands.len() != inputs + outputs {
+        return Err(format!("{} takes {} operands, got {}", mnemonic, inputs + outputs, operands.len()));
+    }
+    let mut code = opcode;
+    for (n, operand) in operands.iter().enumerate() {
+        let mode = match operand {
+            Operand::Position(_) => 0,
+            Operand::Immediate(_) if n >= inputs => return Err(format!("output operand {} of {} is immediate", n + 1, mnemonic)),
+            Operand::Immediate(_) => 1,
+            Operand::Relative(_) => 2,
+        };
+        code += mode * 10_i64.pow(n as u32 + 2);
+    }
+    cells.push(Value::Const(code));
+    cells.extend(operands.iter().map(|operand| match operand {
+        Operand::Position(v) | Operand::Immediate(v) => v.clone(),
+        Operand::Relative(offset) => Value::Const(*offset),
+    }));
+    Ok(())
+}
+
+// Stack pseudo instructions: the relative base points to the first free cell of a stack growing up
+fn expand(mnemonic: &str, operands: Vec<Operand>, cells: &mut Vec<Value>) -> Result<(), String> {
+    let top = Operand::Relative(0);
+    match (mnemonic, operands.as_slice()) {
+        ("push", [value]) => {
+            encode("add", &[value.clone(), Operand::Immediate(Value::Const(0)), top], cells)?;
+            encode("arb", &[Operand::Immediate(Value::Const(1))], cells)
+        }
+        ("pop", [target]) => {
+            encode("arb", &[Operand::Immediate(Value::Const(-1))], cells)?;
+            encode("add", &[top, Operand::Immediate(Value::Const(0)), target.clone()], cells)
+        }
+        // Pushes the return address, the 9 cells of the call itself, and jumps
+        ("call", [target]) => {
+            let return_addr = Operand::Immediate(Value::Address(cells.len() + 9));
+            encode("add", &[return_addr, Operand::Immediate(Value::Const(0)), top], cells)?;
+            encode("arb", &[Operand::Immediate(Value::Const(1))], cells)?;
+            encode("jnz", &[Operand::Immediate(Value::Const(1)), target.clone()], cells)
+        }
+        ("ret", []) => {
+            encode("arb", &[Operand::Immediate(Value::Const(-1))], cells)?;
+            encode("jnz", &[Operand::Immediate(Value::Const(1)), top], cells)
+        }
+        ("push" | "pop" | "call", _) => Err(format!("{} takes 1 operand", mnemonic)),
+        ("ret", _) => Err(String::from("ret takes no operand")),
+        _ => encode(mnemonic, &operands, cells),
+    }
+}
+
+fn split_operands(s: &str) -> Vec<&str> {
+    if s.trim().is_empty() {
+        Vec::new()
+    } else {
+        s.split(',').collect()
+    }
+}
+
+// One instruction or directive per line, optionally preceded by `label:`, `#` starts a comment.
+//
+//   loop: add [counter], -1, [counter]    position operands in brackets
+//         jnz [counter], loop             labels and numbers alone are immediate
+//         out rb[-2]                      relative to the relative base
+//         data 1, 2, table+3              raw cells
+//         space 16                        16 zero cells
+//
+// Besides the Intcode instructions (add mul in out jnz jz lt eq arb halt), `push`, `pop`,
+// `call` and `ret` use the relative base as a stack pointer.
+pub fn assemble(name: &str, source: &str) -> Result<Module, AsmError> {
+    let mut cells: Vec<Value> = Vec::new();
+    let mut lines: Vec<usize> = Vec::new();
+    let mut labels: BTreeMap<String, usize> = BTreeMap::new();
+
+    for (n, line) in source.lines().enumerate() {
+        let error = |message: String| AsmError { line: n + 1, message };
+        let mut line = line.split('#').next().unwrap_or("").trim();
+        if let Some((label, rest)) = line.split_once(':') {
+            let label = label.trim();
+            if !is_identifier(label) {
+                return Err(error(format!("invalid label {}", label)));
+            }
+            if labels.insert(label.to_owned(), cells.len()).is_some() {
+                return Err(error(format!("duplicate label {}", label)));
+            }
+            line = rest.trim();
+        }
+        if line.is_empty() {
+            continue;
+        }
+
+        let (mnemonic, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let result = match mnemonic {
+            "data" => split_operands(rest).into_iter()
+                .map(parse_value)
+                .collect::<Result<Vec<_>, _>>()
+                .map(|values| cells.extend(values)),
+            "space" => rest.trim().parse::<usize>()
+                .map(|len| cells.extend((0..len).map(|_| Value::Const(0))))
+                .map_err(|_| format!("invalid size {}", rest.trim())),
+            _ => split_operands(rest).into_iter()
+                .map(parse_operand)
+                .collect::<Result<Vec<_>, _>>()
+                .and_then(|operands| expand(mnemonic, operands, &mut cells)),
+        };
+        result.map_err(error)?;
+        lines.resize(cells.len(), n + 1);
+    }
+
+    let mut module = Module { name: name.to_owned(), ..Module::default() };
+    for (addr, value) in cells.into_iter().enumerate() {
+        let cell = match value {
+            Value::Const(v) => v,
+            Value::Address(target) => {
+                module.relocations.push(addr);
+                target as Int
+            }
+            Value::Symbol(name, addend) => match labels.get(&name) {
+                Some(target) => {
+                    module.relocations.push(addr);
+                    *target as Int + addend
+                }
+                None if name.starts_with('.') => {
+                    return Err(AsmError { line: lines[addr], message: format!("undefined local label {}", name) });
+                }
+                None => {
+                    module.externals.push((addr, name));
+                    addend
+                }
+            },
+        };
+        module.code.push(cell);
+    }
+    module.symbols = labels.into_iter().filter(|(name, _)| !name.starts_with('.')).collect();
+    Ok(module)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::asm::assemble;
+    use crate::intcode::run_with_inputs;
+
+    #[test]
+    fn test_assemble() {
+        let source = "
+            # doubles the input
+            start: in [value]
+                   mul [value], 2, [value]
+                   out [value]
+                   halt
+            value: data 0
+        ";
+        let module = assemble("double", source).unwrap();
+
+        assert_eq!(module.code, vec![3, 9, 1002, 9, 2, 9, 4, 9, 99, 0]);
+        assert_eq!(module.relocations, vec![1, 3, 5, 7]);
+        assert_eq!(module.symbols.get("value"), Some(&9));
+        assert_eq!(run_with_inputs(module.code, vec![21]), vec![42]);
+    }
+
+    #[test]
+    fn test_modes_and_externals() {
+        let module = assemble("m", "add rb[-1], 5, rb[2]\njnz 1, far+2\n.here: data .here-1, 7").unwrap();
+
+        assert_eq!(module.code, vec![21201, -1, 5, 2, 1105, 1, 2, 6, 7]);
+        assert_eq!(module.externals, vec![(6, String::from("far"))]);
+        assert_eq!(module.relocations, vec![7]);
+        assert!(module.symbols.is_empty());
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(assemble("m", "halt\nadd 1, 2, 3").unwrap_err().line, 2);
+        assert!(assemble("m", "nop").is_err());
+        assert!(assemble("m", "add 1, 2").is_err());
+        assert!(assemble("m", "jnz 1, .missing").is_err());
+        assert!(assemble("m", "a: halt\na: halt").is_err());
+        assert!(assemble("m", "push").is_err());
+    }
+}
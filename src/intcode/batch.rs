@@ -0,0 +1,278 @@
+use crate::intcode::cpu::{IntcodeCpu, StopReason};
+use crate::intcode::instruction::Int;
+use crate::intcode::memory::Memory;
+use crate::intcode::panics::catch_panic;
+use crate::intcode::patch::{PatchError, PatchSet};
+use rayon::prelude::*;
+use rayon::ThreadPool;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::Arc;
+
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub program: Arc<[Int]>,
+    pub patches: Vec<(usize, Int)>,
+    pub inputs: Vec<Int>,
+    pub step_budget: Option<usize>,
+}
+
+impl Job {
+    pub fn new(program: Arc<[Int]>) -> Self {
+        Job { program, patches: Vec::new(), inputs: Vec::new(), step_budget: None }
+    }
+
+    pub fn patch(mut self, addr: usize, value: Int) -> Self {
+        self.patches.push((addr, value));
+        self
+    }
+
+    // Checked like PatchSet::apply, against the program of the job
+    pub fn patch_set(mut self, set: &PatchSet) -> Result<Self, PatchError> {
+        set.validate(self.program.len())?;
+        self.patches.extend(set.patches.iter().map(|patch| (patch.address, patch.value)));
+        Ok(self)
+    }
+
+    pub fn inputs(mut self, inputs: Vec<Int>) -> Self {
+        self.inputs = inputs;
+        self
+    }
+
+    pub fn step_budget(mut self, budget: usize) -> Self {
+        self.step_budget = Some(budget);
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct JobResult {
+    pub cpu: IntcodeCpu,
+    // Interpreter errors (bad opcode, patch outside memory) end only their own job, with the message
+    pub stop_reason: Result<StopReason, String>,
+}
+
+pub struct BatchRunner {
+    pool: Option<Arc<ThreadPool>>,
+    memory_size: usize,
+}
+
+impl Default for BatchRunner {
+    fn default() -> Self {
+        BatchRunner::new()
+    }
+}
+
+impl BatchRunner {
+    pub fn new() -> Self {
+        BatchRunner { pool: None, memory_size: 64 * 1024 }
+    }
+
+    pub fn with_pool(pool: ThreadPool) -> Self {
+        BatchRunner { pool: Some(Arc::new(pool)), ..BatchRunner::new() }
+    }
+
+    pub fn memory_size(mut self, memory_size: usize) -> Self {
+        self.memory_size = memory_size;
+        self
+    }
+
+    pub fn run_all<I>(&self, jobs: I) -> Vec<JobResult>
+    where
+        I: IntoIterator<Item = Job>,
+    {
+        let jobs = prepare_jobs(jobs, self.memory_size);
+        let run = move || {
+            jobs.into_par_iter()
+                .map(|(memory, job)| run_job(memory, job))
+                .collect()
+        };
+        match &self.pool {
+            Some(pool) => pool.install(run),
+            None => run(),
+        }
+    }
+
+    pub fn run_stream<I>(&self, jobs: I) -> OrderedResults
+    where
+        I: IntoIterator<Item = Job>,
+    {
+        let jobs = prepare_jobs(jobs, self.memory_size);
+        let (sender, receiver) = channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let run = {
+            let cancelled = cancelled.clone();
+            move || {
+                jobs.into_par_iter()
+                    .enumerate()
+                    .for_each_with(sender, |sender, (index, (memory, job))| {
+                        // Once the results are dropped, the jobs not started yet are skipped
+                        if !cancelled.load(Ordering::Relaxed) {
+                            let _ = sender.send((index, run_job(memory, job)));
+                        }
+                    })
+            }
+        };
+        match &self.pool {
+            Some(pool) => pool.spawn(run),
+            None => rayon::spawn(run),
+        }
+        OrderedResults { receiver, next_index: 0, pending: BTreeMap::new(), cancelled }
+    }
+}
+
+// Jobs sharing the same program fork a single paged memory built once for that program
+fn prepare_jobs<I>(jobs: I, memory_size: usize) -> Vec<(Memory, Job)>
+where
+    I: IntoIterator<Item = Job>,
+{
+    let mut templates: HashMap<*const Int, Memory> = HashMap::new();
+    jobs.into_iter()
+        .map(|job| {
+            let template = templates.entry(job.program.as_ptr()).or_insert_with(|| {
+                Memory::from_slice(&job.program, memory_size.max(job.program.len()))
+            });
+            (template.fork(), job)
+        })
+        .collect()
+}
+
+fn run_job(memory: Memory, job: Job) -> JobResult {
+    let Job { patches, inputs, step_budget, .. } = job;
+    let mut cpu = IntcodeCpu::new_with_memory(memory, inputs);
+    let budget = step_budget.unwrap_or(usize::MAX);
+    let stop_reason = catch_panic(|| {
+        for (addr, value) in patches {
+            cpu.memory[addr] = value;
+        }
+        cpu.run_with_budget(budget)
+    });
+    JobResult { cpu, stop_reason }
+}
+
+// Yields results in job order, buffering the ones completed ahead of time. Dropping it cancels
+// the jobs that have not started.
+pub struct OrderedResults {
+    receiver: Receiver<(usize, JobResult)>,
+    next_index: usize,
+    pending: BTreeMap<usize, JobResult>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Drop for OrderedResults {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Iterator for OrderedResults {
+    type Item = JobResult;
+
+    fn next(&mut self) -> Option<JobResult> {
+        loop {
+            if let Some(result) = self.pending.remove(&self.next_index) {
+                self.next_index += 1;
+                return Some(result);
+            }
+            let (index, result) = self.receiver.recv().ok()?;
+            self.pending.insert(index, result);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::batch::{BatchRunner, Job};
+    use crate::intcode::patch::{PatchError, PatchSet};
+    use crate::intcode::{parse_intcode_program, StopReason};
+    use std::sync::Arc;
+
+    fn comparator() -> Arc<[i64]> {
+        let input = "3,9,8,9,10,9,4,9,99,-1,8";
+        Arc::from(parse_intcode_program(input).unwrap())
+    }
+
+    #[test]
+    fn test_run_all_in_order() {
+        let program = comparator();
+        let jobs = (0..100).map(|i| Job::new(program.clone()).inputs(vec![i % 10]));
+
+        let results = BatchRunner::new().run_all(jobs);
+
+        assert_eq!(results.len(), 100);
+        for (i, result) in results.iter().enumerate() {
+            let expected = if i % 10 == 8 { 1 } else { 0 };
+            assert_eq!(result.cpu.outputs, vec![expected]);
+            assert_eq!(result.stop_reason, Ok(StopReason::Halted));
+        }
+    }
+
+    #[test]
+    fn test_stream_in_order() {
+        let program = comparator();
+        let jobs = (0..100).map(|i| Job::new(program.clone()).patch(10, i).inputs(vec![i]));
+
+        let outputs: Vec<_> = BatchRunner::new().run_stream(jobs)
+            .map(|r| r.cpu.outputs[0])
+            .collect();
+
+        assert_eq!(outputs, vec![1; 100]);
+    }
+
+    #[test]
+    fn test_patches_do_not_leak_between_jobs() {
+        let program: Arc<[i64]> = Arc::from(parse_intcode_program("1,0,0,0,99").unwrap());
+        let jobs = vec![
+            Job::new(program.clone()).patch(1, 4),
+            Job::new(program.clone()),
+        ];
+
+        let results = BatchRunner::new().run_all(jobs);
+
+        assert_eq!(results[0].cpu.memory[0], 100);
+        assert_eq!(results[1].cpu.memory[0], 2);
+        assert_eq!(program[0], 1);
+    }
+
+    #[test]
+    fn test_patch_set() {
+        let program: Arc<[i64]> = Arc::from(parse_intcode_program("1,0,0,0,99").unwrap());
+        let set = PatchSet::new("test").with("noun", 1, 4).with("verb", 2, 4);
+        let results = BatchRunner::new().run_all(vec![Job::new(program.clone()).patch_set(&set).unwrap()]);
+        assert_eq!(results[0].cpu.memory[0], 198);
+
+        let set = PatchSet::new("test").with("far", 5, 1);
+        let error = Job::new(program).patch_set(&set).unwrap_err();
+        assert_eq!(error, PatchError::OutOfRange { label: String::from("far"), address: 5, len: 5 });
+    }
+
+    #[test]
+    fn test_step_budget() {
+        let program: Arc<[i64]> = Arc::from(parse_intcode_program("1105,1,0").unwrap());
+        let results = BatchRunner::new().run_all(vec![Job::new(program).step_budget(10)]);
+
+        assert_eq!(results[0].stop_reason, Ok(StopReason::OutOfBudget));
+        assert_eq!(results[0].cpu.steps, 10);
+    }
+
+    #[test]
+    fn test_errors_stay_in_their_job() {
+        let program = comparator();
+        let jobs = vec![
+            Job::new(program.clone()).inputs(vec![8]),
+            Job::new(program.clone()).patch(0, 42).inputs(vec![8]),
+            Job::new(program.clone()).patch(1 << 20, 1),
+            Job::new(program.clone()).inputs(vec![8]),
+        ];
+
+        let results: Vec<_> = BatchRunner::new().run_stream(jobs.clone()).collect();
+        assert_eq!(results.len(), 4);
+        assert!(results[1].stop_reason.is_err() && results[2].stop_reason.is_err());
+        assert_eq!(results[3].cpu.outputs, vec![1]);
+
+        let results = BatchRunner::new().run_all(jobs);
+        assert_eq!(results[0].stop_reason, Ok(StopReason::Halted));
+        assert!(results[1].stop_reason.is_err());
+    }
+}
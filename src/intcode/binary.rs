@@ -0,0 +1,451 @@
+use crate::intcode::cpu::IntcodeCpu;
+use crate::intcode::instruction::Int;
+use crate::intcode::link::{Image, Section};
+use crate::intcode::memory::{Memory, MAX_GROWN_SIZE};
+use crate::intcode::parser::{parse_intcode_program, ParseError};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt::{Display, Formatter, Write};
+use std::fs;
+use std::path::Path;
+
+// Layout, every number being a LEB128 varint unless noted:
+//
+//   magic "ICIM" (4 bytes), version (1 byte), word size in bytes (1 byte)
+//   memory size, entry pc + 1 or 0 when missing
+//   section count, then name, start, len for each
+//   symbol count, then name, address for each
+//   program length, then the cells zigzag encoded
+//
+// Names are a length followed by UTF-8 bytes.
+pub const MAGIC: [u8; 4] = *b"ICIM";
+pub const VERSION: u8 = 1;
+pub const WORD_SIZE: u8 = std::mem::size_of::<Int>() as u8;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ImageError {
+    BadMagic,
+    UnsupportedVersion(u8),
+    UnsupportedWordSize(u8),
+    // `offset` is a byte offset in the binary form
+    Truncated { offset: usize },
+    InvalidVarint { offset: usize },
+    InvalidName { offset: usize },
+    TrailingBytes { offset: usize },
+    Invalid(String),
+    Parse(ParseError),
+    Metadata { line: usize, message: String },
+}
+
+impl Display for ImageError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImageError::BadMagic => write!(f, "not an Intcode image"),
+            ImageError::UnsupportedVersion(v) => write!(f, "unsupported image version {}", v),
+            ImageError::UnsupportedWordSize(size) => write!(f, "unsupported word size of {} bytes", size),
+            ImageError::Truncated { offset } => write!(f, "image truncated at byte {}", offset),
+            ImageError::InvalidVarint { offset } => write!(f, "invalid number at byte {}", offset),
+            ImageError::InvalidName { offset } => write!(f, "invalid name at byte {}", offset),
+            ImageError::TrailingBytes { offset } => write!(f, "unexpected data after the image at byte {}", offset),
+            ImageError::Invalid(message) => write!(f, "{}", message),
+            ImageError::Parse(e) => write!(f, "{}", e),
+            ImageError::Metadata { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for ImageError {}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct BinaryImage {
+    // Cells the program needs, at least the length of the program
+    pub memory_size: usize,
+    pub entry: Option<usize>,
+    pub sections: Vec<Section>,
+    pub symbols: BTreeMap<String, usize>,
+    pub program: Vec<Int>,
+}
+
+fn write_varint(out: &mut Vec<u8>, mut v: u64) {
+    while v >= 0x80 {
+        out.push(v as u8 | 0x80);
+        v >>= 7;
+    }
+    out.push(v as u8);
+}
+
+fn write_name(out: &mut Vec<u8>, name: &str) {
+    write_varint(out, name.len() as u64);
+    out.extend_from_slice(name.as_bytes());
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8], ImageError> {
+        let end = self.pos.checked_add(len).filter(|end| *end <= self.bytes.len())
+            .ok_or(ImageError::Truncated { offset: self.bytes.len() })?;
+        let bytes = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, ImageError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn varint(&mut self) -> Result<u64, ImageError> {
+        let start = self.pos;
+        let mut v = 0_u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            if shift == 63 && byte > 1 {
+                break;
+            }
+            v |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(v);
+            }
+        }
+        Err(ImageError::InvalidVarint { offset: start })
+    }
+
+    fn usize(&mut self) -> Result<usize, ImageError> {
+        let start = self.pos;
+        let v = self.varint()?;
+        usize::try_from(v).map_err(|_| ImageError::InvalidVarint { offset: start })
+    }
+
+    fn name(&mut self) -> Result<String, ImageError> {
+        let start = self.pos;
+        let len = self.usize()?;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| ImageError::InvalidName { offset: start })
+    }
+}
+
+fn zigzag(v: Int) -> u64 {
+    ((v << 1) ^ (v >> 63)) as u64
+}
+
+fn unzigzag(v: u64) -> Int {
+    (v >> 1) as Int ^ -((v & 1) as Int)
+}
+
+fn is_name(s: &str) -> bool {
+    !s.is_empty() && !s.contains(char::is_whitespace)
+}
+
+impl BinaryImage {
+    pub fn from_program(program: Vec<Int>) -> Self {
+        BinaryImage {
+            memory_size: program.len(),
+            entry: None,
+            sections: Vec::new(),
+            symbols: BTreeMap::new(),
+            program,
+        }
+    }
+
+    // A linked image starts at address 0, the memory after it is free for the stack
+    pub fn from_linked(image: &Image, memory_size: usize) -> Self {
+        BinaryImage {
+            memory_size: memory_size.max(image.program.len()),
+            entry: Some(0),
+            sections: image.sections.clone(),
+            symbols: image.symbols.clone(),
+            program: image.program.clone(),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ImageError> {
+        let invalid = |message: String| Err(ImageError::Invalid(message));
+        // The memory could not grow that far either, mostly guards against corrupted sizes
+        if self.memory_size > MAX_GROWN_SIZE {
+            return invalid(format!("memory size {} exceeds the limit of {} cells", self.memory_size, MAX_GROWN_SIZE));
+        }
+        if self.program.len() > self.memory_size {
+            return invalid(format!("program of {} cells exceeds the memory size {}", self.program.len(), self.memory_size));
+        }
+        if let Some(entry) = self.entry.filter(|entry| *entry >= self.program.len()) {
+            return invalid(format!("entry pc {} is outside of the program", entry));
+        }
+        for (i, section) in self.sections.iter().enumerate() {
+            if !is_name(&section.name) {
+                return invalid(format!("invalid section name {:?}", section.name));
+            }
+            if self.sections[..i].iter().any(|s| s.name == section.name) {
+                return invalid(format!("duplicate section {}", section.name));
+            }
+            if section.start.checked_add(section.len).is_none_or(|end| end > self.program.len()) {
+                return invalid(format!("section {} is outside of the program", section.name));
+            }
+        }
+        for (name, addr) in &self.symbols {
+            if !is_name(name) {
+                return invalid(format!("invalid symbol name {:?}", name));
+            }
+            if *addr > self.memory_size {
+                return invalid(format!("symbol {} at {} is outside of the memory", name, addr));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        out.push(VERSION);
+        out.push(WORD_SIZE);
+        write_varint(&mut out, self.memory_size as u64);
+        write_varint(&mut out, self.entry.map_or(0, |entry| entry as u64 + 1));
+        write_varint(&mut out, self.sections.len() as u64);
+        for section in &self.sections {
+            write_name(&mut out, &section.name);
+            write_varint(&mut out, section.start as u64);
+            write_varint(&mut out, section.len as u64);
+        }
+        write_varint(&mut out, self.symbols.len() as u64);
+        for (name, addr) in &self.symbols {
+            write_name(&mut out, name);
+            write_varint(&mut out, *addr as u64);
+        }
+        write_varint(&mut out, self.program.len() as u64);
+        for v in &self.program {
+            write_varint(&mut out, zigzag(*v));
+        }
+        out
+    }
+
+    pub fn is_binary(bytes: &[u8]) -> bool {
+        bytes.starts_with(&MAGIC)
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, ImageError> {
+        if !Self::is_binary(bytes) {
+            return Err(ImageError::BadMagic);
+        }
+        let mut reader = Reader { bytes, pos: MAGIC.len() };
+        let version = reader.byte()?;
+        if version != VERSION {
+            return Err(ImageError::UnsupportedVersion(version));
+        }
+        let word_size = reader.byte()?;
+        if word_size != WORD_SIZE {
+            return Err(ImageError::UnsupportedWordSize(word_size));
+        }
+
+        let memory_size = reader.usize()?;
+        let entry = reader.usize()?.checked_sub(1);
+        let mut sections = Vec::new();
+        for _ in 0..reader.usize()? {
+            let name = reader.name()?;
+            sections.push(Section { name, start: reader.usize()?, len: reader.usize()? });
+        }
+        let mut symbols = BTreeMap::new();
+        for _ in 0..reader.usize()? {
+            let name = reader.name()?;
+            let addr = reader.usize()?;
+            if symbols.contains_key(&name) {
+                return Err(ImageError::Invalid(format!("duplicate symbol {}", name)));
+            }
+            symbols.insert(name, addr);
+        }
+        // Every cell takes at least a byte, bounds the allocation on corrupted lengths
+        let len = reader.usize()?;
+        if len > bytes.len() - reader.pos {
+            return Err(ImageError::Truncated { offset: bytes.len() });
+        }
+        let program = (0..len).map(|_| reader.varint().map(unzigzag)).collect::<Result<Vec<_>, _>>()?;
+        if reader.pos != bytes.len() {
+            return Err(ImageError::TrailingBytes { offset: reader.pos });
+        }
+
+        let image = BinaryImage { memory_size, entry, sections, symbols, program };
+        image.validate()?;
+        Ok(image)
+    }
+
+    // The usual comma separated program, the metadata in `#@` comment lines ignored by the parser:
+    //
+    //   #@ memory 65536
+    //   #@ entry 0
+    //   #@ section main 0 12
+    //   #@ symbol loop 4
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        let _ = writeln!(text, "#@ memory {}", self.memory_size);
+        if let Some(entry) = self.entry {
+            let _ = writeln!(text, "#@ entry {}", entry);
+        }
+        for section in &self.sections {
+            let _ = writeln!(text, "#@ section {} {} {}", section.name, section.start, section.len);
+        }
+        for (name, addr) in &self.symbols {
+            let _ = writeln!(text, "#@ symbol {} {}", name, addr);
+        }
+        let values: Vec<String> = self.program.iter().map(|v| v.to_string()).collect();
+        let _ = writeln!(text, "{}", values.join(","));
+        text
+    }
+
+    // Plain programs without metadata are accepted, their memory size is their length
+    pub fn from_text(source: &str) -> Result<Self, ImageError> {
+        let program = parse_intcode_program(source).map_err(ImageError::Parse)?;
+        let mut image = BinaryImage::from_program(program);
+        let mut memory_size = None;
+
+        for (n, line) in source.lines().enumerate() {
+            let directive = match line.trim().strip_prefix("#@") {
+                Some(directive) => directive,
+                None => continue,
+            };
+            let metadata = |message: String| ImageError::Metadata { line: n + 1, message };
+            let words: Vec<&str> = directive.split_whitespace().collect();
+            let number = |word: &str| word.parse::<usize>().map_err(|_| metadata(format!("invalid number {}", word)));
+            match words.as_slice() {
+                ["memory", size] => memory_size = Some(number(size)?),
+                ["entry", pc] => image.entry = Some(number(pc)?),
+                ["section", name, start, len] => {
+                    let section = Section { name: name.to_string(), start: number(start)?, len: number(len)? };
+                    image.sections.push(section);
+                }
+                ["symbol", name, addr] => {
+                    if image.symbols.insert(name.to_string(), number(addr)?).is_some() {
+                        return Err(metadata(format!("duplicate symbol {}", name)));
+                    }
+                }
+                _ => return Err(metadata(format!("invalid metadata {}", directive.trim()))),
+            }
+        }
+
+        image.memory_size = memory_size.unwrap_or(image.memory_size);
+        image.validate()?;
+        Ok(image)
+    }
+
+    // Loads the memory, of at least `memory_size` cells, and starts at the entry pc
+    pub fn cpu(&self, memory_size: usize, inputs: Vec<Int>) -> IntcodeCpu {
+        let memory = Memory::from_slice(&self.program, memory_size.max(self.memory_size));
+        let mut cpu = IntcodeCpu::new_with_memory(memory, inputs);
+        cpu.pc = self.entry.unwrap_or(0);
+        cpu
+    }
+
+    // Closest symbol at or before the address, with the offset from it
+    pub fn locate(&self, addr: usize) -> Option<(&str, usize)> {
+        self.symbols.iter()
+            .filter(|(_, start)| **start <= addr)
+            .max_by_key(|(_, start)| **start)
+            .map(|(name, start)| (name.as_str(), addr - start))
+    }
+
+    pub fn section_at(&self, addr: usize) -> Option<&Section> {
+        self.sections.iter().find(|section| section.start <= addr && addr < section.start + section.len)
+    }
+}
+
+// Binary or text, told apart by the magic number
+pub fn load_image(path: &Path) -> Result<BinaryImage, String> {
+    let bytes = fs::read(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    parse_image(&bytes).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+pub fn parse_image(bytes: &[u8]) -> Result<BinaryImage, ImageError> {
+    if BinaryImage::is_binary(bytes) {
+        BinaryImage::decode(bytes)
+    } else {
+        let source = std::str::from_utf8(bytes).map_err(|_| ImageError::BadMagic)?;
+        BinaryImage::from_text(source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::binary::{parse_image, BinaryImage, ImageError, MAGIC};
+    use crate::intcode::link::Section;
+    use crate::intcode::stdlib::build;
+    use crate::intcode::parse_intcode_program;
+
+    #[test]
+    fn test_round_trip() {
+        let source = "main:\n  in rb[0]\n  out rb[0]\nloop: jnz 1, loop\n  halt";
+        let image = BinaryImage::from_linked(&build("main", source).unwrap(), 4096);
+        let bytes = image.encode();
+
+        assert!(bytes.starts_with(&MAGIC));
+        assert_eq!(BinaryImage::decode(&bytes), Ok(image.clone()));
+        assert_eq!(BinaryImage::from_text(&image.to_text()), Ok(image.clone()));
+        assert_eq!(image.locate(image.symbols["loop"] + 1), Some(("loop", 1)));
+        assert_eq!(image.section_at(0).map(|s| s.name.as_str()), Some("main"));
+
+        let program = parse_intcode_program(include_str!("../inputs/day9.txt")).unwrap();
+        let image = BinaryImage::from_program(program.clone());
+        assert!(image.encode().len() < include_str!("../inputs/day9.txt").len() / 2);
+        assert_eq!(parse_image(&image.encode()).unwrap().program, program);
+        assert_eq!(parse_image(b"1,-2,99\n").unwrap().program, vec![1, -2, 99]);
+    }
+
+    #[test]
+    fn test_negative_and_large_values() {
+        let image = BinaryImage::from_program(vec![0, -1, 1, i64::MIN, i64::MAX, 99]);
+        assert_eq!(BinaryImage::decode(&image.encode()), Ok(image));
+    }
+
+    #[test]
+    fn test_invalid_images() {
+        let mut image = BinaryImage::from_program(vec![99]);
+        image.symbols.insert(String::from("start"), 0);
+        let bytes = image.encode();
+
+        assert_eq!(BinaryImage::decode(b"ICIX"), Err(ImageError::BadMagic));
+        assert_eq!(BinaryImage::decode(&bytes[..bytes.len() - 1]), Err(ImageError::Truncated { offset: bytes.len() - 1 }));
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(BinaryImage::decode(&trailing), Err(ImageError::TrailingBytes { offset: bytes.len() }));
+        let mut version = bytes.clone();
+        version[4] = 9;
+        assert_eq!(BinaryImage::decode(&version), Err(ImageError::UnsupportedVersion(9)));
+
+        image.entry = Some(5);
+        assert!(matches!(BinaryImage::decode(&image.encode()), Err(ImageError::Invalid(_))));
+        assert!(matches!(BinaryImage::from_text("#@ memory 1\n1,2,3"), Err(ImageError::Invalid(_))));
+        assert!(matches!(BinaryImage::from_text("#@ stack 1\n99"), Err(ImageError::Metadata { line: 1, .. })));
+    }
+
+    #[test]
+    fn test_memory_size_limit() {
+        let mut image = BinaryImage::from_program(vec![99]);
+        image.memory_size = usize::MAX;
+        let error = BinaryImage::decode(&image.encode()).unwrap_err();
+        assert!(error.to_string().starts_with("memory size"), "{}", error);
+        assert!(BinaryImage::from_text("#@ memory 1000000000000\n99").is_err());
+    }
+
+    #[test]
+    fn test_duplicate_names() {
+        let mut image = BinaryImage::from_program(vec![99]);
+        image.sections.push(Section { name: String::from("main"), start: 0, len: 1 });
+        image.sections.push(Section { name: String::from("main"), start: 0, len: 1 });
+        let error = BinaryImage::decode(&image.encode()).unwrap_err();
+        assert_eq!(error, ImageError::Invalid(String::from("duplicate section main")));
+
+        // A BTreeMap cannot hold the same symbol twice, so encode one and repeat it by hand
+        let mut image = BinaryImage::from_program(vec![99]);
+        image.symbols.insert(String::from("a"), 0);
+        let bytes = image.encode();
+        // The symbol count follows the version, word size, memory size, entry and section count, a byte each here
+        let start = MAGIC.len() + 5;
+        let entry = bytes[start + 1..start + 4].to_vec();
+        let mut twice = bytes[..start].to_vec();
+        twice.push(2);
+        twice.extend_from_slice(&entry);
+        twice.extend_from_slice(&entry);
+        twice.extend_from_slice(&bytes[start + 4..]);
+        let error = BinaryImage::decode(&twice).unwrap_err();
+        assert_eq!(error, ImageError::Invalid(String::from("duplicate symbol a")));
+    }
+}
@@ -0,0 +1,238 @@
+use crate::intcode::cpu::{IntcodeCpu, StopReason};
+use crate::intcode::instruction::Int;
+use crate::intcode::memory::Memory;
+use crate::intcode::panics::catch_panic;
+use crate::intcode::parser::parse_intcode_program;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// A declarative Intcode test case, one `key: value` per line, `#` starts a comment line:
+//
+//   program: 3,9,8,9,10,9,4,9,99,-1,8    (or program_file: path relative to the case file)
+//   inputs: 8
+//   outputs: 1
+//   memory: 9=1
+//   halt: halted                          (halted, waiting_for_input, out_of_budget or error)
+//   memory_size: 1024
+//   max_steps: 100
+//
+// Only the expectations present in the file are checked.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestCase {
+    pub name: String,
+    pub program: Vec<Int>,
+    pub inputs: Vec<Int>,
+    pub memory_size: usize,
+    pub max_steps: Option<usize>,
+    pub expected_outputs: Option<Vec<Int>>,
+    pub expected_memory: Vec<(usize, Int)>,
+    pub expected_halt: Option<Halt>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Halt {
+    Stopped(StopReason),
+    Error,
+}
+
+impl Halt {
+    fn parse(s: &str) -> Result<Halt, String> {
+        match s {
+            "halted" => Ok(Halt::Stopped(StopReason::Halted)),
+            "waiting_for_input" => Ok(Halt::Stopped(StopReason::WaitingForInput)),
+            "out_of_budget" => Ok(Halt::Stopped(StopReason::OutOfBudget)),
+            "error" => Ok(Halt::Error),
+            _ => Err(format!("unknown halt state {}", s)),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Halt::Stopped(StopReason::Halted) => "halted",
+            Halt::Stopped(StopReason::WaitingForInput) => "waiting_for_input",
+            Halt::Stopped(StopReason::OutOfBudget) => "out_of_budget",
+            Halt::Error => "error",
+        }
+    }
+}
+
+fn parse_ints(value: &str) -> Result<Vec<Int>, String> {
+    parse_intcode_program(value).map_err(|e| e.to_string())
+}
+
+fn parse_memory(value: &str) -> Result<Vec<(usize, Int)>, String> {
+    value.split(',')
+        .map(|cell| cell.trim())
+        .filter(|cell| !cell.is_empty())
+        .map(|cell| {
+            let (addr, v) = cell.split_once('=').ok_or(format!("expected addr=value, got {}", cell))?;
+            let addr = addr.trim().parse().map_err(|_| format!("invalid address {}", addr))?;
+            let v = v.trim().parse().map_err(|_| format!("invalid value {}", v))?;
+            Ok((addr, v))
+        })
+        .collect()
+}
+
+impl TestCase {
+    pub fn parse(name: &str, source: &str, base_dir: &Path) -> Result<TestCase, String> {
+        let mut case = TestCase {
+            name: name.to_owned(),
+            program: Vec::new(),
+            inputs: Vec::new(),
+            memory_size: 64 * 1024,
+            max_steps: None,
+            expected_outputs: None,
+            expected_memory: Vec::new(),
+            expected_halt: None,
+        };
+        let mut has_program = false;
+
+        for (n, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line.split_once(':')
+                .ok_or(format!("line {}: expected key: value", n + 1))?;
+            let value = value.trim();
+            let parsed = match key.trim() {
+                "program" => parse_ints(value).map(|p| case.program = p),
+                "program_file" => {
+                    let path = base_dir.join(value);
+                    fs::read_to_string(&path)
+                        .map_err(|e| format!("cannot read {}: {}", path.display(), e))
+                        .and_then(|source| parse_ints(&source))
+                        .map(|p| case.program = p)
+                }
+                "inputs" => parse_ints(value).map(|i| case.inputs = i),
+                "outputs" => parse_ints(value).map(|o| case.expected_outputs = Some(o)),
+                "memory" => parse_memory(value).map(|m| case.expected_memory.extend(m)),
+                "halt" => Halt::parse(value).map(|h| case.expected_halt = Some(h)),
+                "memory_size" => value.parse().map(|s| case.memory_size = s).map_err(|e| format!("{}", e)),
+                "max_steps" => value.parse().map(|s| case.max_steps = Some(s)).map_err(|e| format!("{}", e)),
+                other => Err(format!("unknown key {}", other)),
+            };
+            parsed.map_err(|e| format!("line {}: {}", n + 1, e))?;
+            has_program |= key.trim().starts_with("program");
+        }
+
+        if has_program {
+            Ok(case)
+        } else {
+            Err(String::from("missing program"))
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<TestCase, String> {
+        let source = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+        let name = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+        let base_dir = path.parent().unwrap_or_else(|| Path::new("."));
+        TestCase::parse(&name, &source, base_dir)
+    }
+
+    // Runs the case and returns a description of every expectation that was not met
+    pub fn run(&self) -> Vec<String> {
+        let size = self.memory_size.max(self.program.len());
+        let mut cpu = IntcodeCpu::new_with_memory(Memory::from_slice(&self.program, size), self.inputs.clone());
+        let budget = self.max_steps.unwrap_or(usize::MAX);
+        let halt = match catch_panic(|| cpu.run_with_budget(budget)) {
+            Ok(reason) => Halt::Stopped(reason),
+            Err(_) => Halt::Error,
+        };
+
+        let mut failures = Vec::new();
+        if let Some(expected) = &self.expected_halt {
+            if *expected != halt {
+                failures.push(format!("halt: expected {}, got {}", expected.name(), halt.name()));
+            }
+        }
+        if let Some(expected) = &self.expected_outputs {
+            if *expected != cpu.outputs {
+                failures.push(diff_outputs(expected, &cpu.outputs));
+            }
+        }
+        for (addr, expected) in &self.expected_memory {
+            let actual = if *addr < cpu.memory.len() { Some(cpu.memory[*addr]) } else { None };
+            if actual != Some(*expected) {
+                let actual = actual.map(|v| v.to_string()).unwrap_or_else(|| String::from("out of bounds"));
+                failures.push(format!("memory[{}]: expected {}, got {}", addr, expected, actual));
+            }
+        }
+        failures
+    }
+}
+
+fn diff_outputs(expected: &[Int], actual: &[Int]) -> String {
+    let first_difference = expected.iter().zip(actual)
+        .position(|(e, a)| e != a)
+        .unwrap_or_else(|| expected.len().min(actual.len()));
+    format!(
+        "outputs differ at index {}:\n  - expected: {:?}\n  + actual:   {:?}",
+        first_difference, expected, actual
+    )
+}
+
+#[derive(Debug)]
+pub struct CaseResult {
+    pub path: PathBuf,
+    pub failures: Vec<String>,
+}
+
+impl CaseResult {
+    pub fn is_pass(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+// Loads and runs every `*.case` file of a directory, in file name order
+pub fn run_corpus(dir: &Path) -> Result<Vec<CaseResult>, String> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(|e| format!("cannot read {}: {}", dir.display(), e))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().map(|ext| ext == "case").unwrap_or(false))
+        .collect();
+    paths.sort();
+
+    Ok(paths.into_iter()
+        .map(|path| {
+            let failures = match TestCase::load(&path) {
+                Ok(case) => case.run(),
+                Err(e) => vec![format!("invalid case: {}", e)],
+            };
+            CaseResult { path, failures }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::case::{Halt, TestCase};
+    use crate::intcode::StopReason;
+    use std::path::Path;
+
+    #[test]
+    fn test_parse_and_run() {
+        let source = "# equal to 8\nprogram: 3,9,8,9,10,9,4,9,99,-1,8\ninputs: 8\noutputs: 1\nmemory: 9=1, 10=8\nhalt: halted\n";
+        let case = TestCase::parse("eq8", source, Path::new(".")).unwrap();
+
+        assert_eq!(case.inputs, vec![8]);
+        assert_eq!(case.expected_halt, Some(Halt::Stopped(StopReason::Halted)));
+        assert!(case.run().is_empty());
+    }
+
+    #[test]
+    fn test_reports_failures() {
+        let source = "program: 104,1,104,2,99\noutputs: 1,3\nmemory: 0=5\nhalt: waiting_for_input\n";
+        let case = TestCase::parse("fail", source, Path::new(".")).unwrap();
+
+        let failures = case.run();
+        assert_eq!(failures.len(), 3);
+        assert!(failures[1].starts_with("outputs differ at index 1"));
+    }
+
+    #[test]
+    fn test_invalid_case() {
+        assert!(TestCase::parse("empty", "inputs: 1\n", Path::new(".")).is_err());
+        assert!(TestCase::parse("bad", "program: 99\nhalt: maybe\n", Path::new(".")).is_err());
+    }
+}
@@ -0,0 +1,654 @@
+use crate::intcode::asm::assemble;
+use crate::intcode::instruction::Int;
+use crate::intcode::link::link;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter, Write};
+
+// A minimal language compiled to Intcode:
+//
+//   fn fib(n) {
+//       if n < 2 { return n; }
+//       return fib(n - 1) + fib(n - 2);
+//   }
+//
+//   fn main() {
+//       let n = input();
+//       while n > 0 { output(fib(n)); n = n - 1; }
+//   }
+//
+// Values are integers, `main` is the entry point, `//` starts a comment. Operators are
+// `|| && == != < <= > >= + - * ! -`, `input()` reads a value and `output(x)` writes one.
+// Variables live in the stack frame of their function, addressed through the relative base.
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CompileError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl Display for CompileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for CompileError {}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Token {
+    Number(Int),
+    Ident(String),
+    Symbol(&'static str),
+}
+
+const SYMBOLS: [&str; 19] = [
+    "==", "!=", "<=", ">=", "&&", "||", "(", ")", "{", "}", ",", ";", "=", "<", ">", "+", "-", "*", "!",
+];
+
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, CompileError> {
+    let mut tokens = Vec::new();
+    for (n, line) in source.lines().enumerate() {
+        let line = line.split("//").next().unwrap_or("");
+        let mut rest = line.trim_start();
+        while !rest.is_empty() {
+            let len = if rest.starts_with(|c: char| c.is_ascii_digit()) {
+                let len = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+                let value = rest[..len].parse()
+                    .map_err(|_| CompileError { line: n + 1, message: format!("number {} is too large", &rest[..len]) })?;
+                tokens.push((Token::Number(value), n + 1));
+                len
+            } else if rest.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+                let len = rest.find(|c: char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(rest.len());
+                tokens.push((Token::Ident(rest[..len].to_owned()), n + 1));
+                len
+            } else if let Some(symbol) = SYMBOLS.iter().find(|s| rest.starts_with(**s)) {
+                tokens.push((Token::Symbol(symbol), n + 1));
+                symbol.len()
+            } else {
+                let c = rest.chars().next().unwrap_or(' ');
+                return Err(CompileError { line: n + 1, message: format!("unexpected character {:?}", c) });
+            };
+            rest = rest[len..].trim_start();
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Number(Int),
+    Var(String, usize),
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>, usize),
+    Input,
+    Output(Box<Expr>),
+}
+
+#[derive(Debug, Clone)]
+enum Stmt {
+    Let(String, Expr),
+    Assign(String, Expr, usize),
+    If(Expr, Vec<Stmt>, Vec<Stmt>),
+    While(Expr, Vec<Stmt>),
+    Return(Option<Expr>),
+    Expr(Expr),
+}
+
+#[derive(Debug, Clone)]
+struct Function {
+    name: String,
+    params: Vec<String>,
+    body: Vec<Stmt>,
+}
+
+// Binary operators from the loosest to the tightest binding
+const PRECEDENCE: [&[&str]; 5] = [&["||"], &["&&"], &["==", "!="], &["<", "<=", ">", ">="], &["+", "-"]];
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn line(&self) -> usize {
+        self.tokens.get(self.pos).or(self.tokens.last()).map(|(_, line)| *line).unwrap_or(1)
+    }
+
+    fn error<T>(&self, message: String) -> Result<T, CompileError> {
+        Err(CompileError { line: self.line(), message })
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn accept(&mut self, symbol: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn accept_keyword(&mut self, keyword: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Ident(s)) if s == keyword);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<(), CompileError> {
+        if self.accept(symbol) {
+            Ok(())
+        } else {
+            self.error(format!("expected {}, found {:?}", symbol, self.peek()))
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, CompileError> {
+        match self.next() {
+            Some(Token::Ident(name)) => Ok(name),
+            other => {
+                self.pos -= 1;
+                self.error(format!("expected a name, found {:?}", other))
+            }
+        }
+    }
+
+    fn function(&mut self) -> Result<Function, CompileError> {
+        if !self.accept_keyword("fn") {
+            return self.error(format!("expected fn, found {:?}", self.peek()));
+        }
+        let name = self.ident()?;
+        self.expect("(")?;
+        let mut params = Vec::new();
+        if !self.accept(")") {
+            loop {
+                params.push(self.ident()?);
+                if self.accept(")") {
+                    break;
+                }
+                self.expect(",")?;
+            }
+        }
+        let body = self.block()?;
+        Ok(Function { name, params, body })
+    }
+
+    fn block(&mut self) -> Result<Vec<Stmt>, CompileError> {
+        self.expect("{")?;
+        let mut statements = Vec::new();
+        while !self.accept("}") {
+            if self.peek().is_none() {
+                return self.error(String::from("unclosed block"));
+            }
+            statements.push(self.statement()?);
+        }
+        Ok(statements)
+    }
+
+    fn statement(&mut self) -> Result<Stmt, CompileError> {
+        if self.accept_keyword("let") {
+            let name = self.ident()?;
+            self.expect("=")?;
+            let value = self.expr()?;
+            self.expect(";")?;
+            Ok(Stmt::Let(name, value))
+        } else if self.accept_keyword("if") {
+            let condition = self.expr()?;
+            let then = self.block()?;
+            let otherwise = if !self.accept_keyword("else") {
+                Vec::new()
+            } else if matches!(self.peek(), Some(Token::Ident(s)) if s == "if") {
+                vec![self.statement()?]
+            } else {
+                self.block()?
+            };
+            Ok(Stmt::If(condition, then, otherwise))
+        } else if self.accept_keyword("while") {
+            let condition = self.expr()?;
+            Ok(Stmt::While(condition, self.block()?))
+        } else if self.accept_keyword("return") {
+            let value = if self.accept(";") { None } else {
+                let value = self.expr()?;
+                self.expect(";")?;
+                Some(value)
+            };
+            Ok(Stmt::Return(value))
+        } else {
+            let line = self.line();
+            let expr = self.expr()?;
+            if self.accept("=") {
+                let name = match expr {
+                    Expr::Var(name, _) => name,
+                    _ => return self.error(String::from("only variables can be assigned")),
+                };
+                let value = self.expr()?;
+                self.expect(";")?;
+                Ok(Stmt::Assign(name, value, line))
+            } else {
+                self.expect(";")?;
+                Ok(Stmt::Expr(expr))
+            }
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, CompileError> {
+        self.binary(0)
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expr, CompileError> {
+        if level == PRECEDENCE.len() {
+            return self.product();
+        }
+        let mut left = self.binary(level + 1)?;
+        while let Some(op) = PRECEDENCE[level].iter().find(|op| matches!(self.peek(), Some(Token::Symbol(s)) if s == *op)) {
+            self.pos += 1;
+            let right = self.binary(level + 1)?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn product(&mut self) -> Result<Expr, CompileError> {
+        let mut left = self.unary()?;
+        while self.accept("*") {
+            left = Expr::Binary("*", Box::new(left), Box::new(self.unary()?));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, CompileError> {
+        if self.accept("-") {
+            Ok(Expr::Neg(Box::new(self.unary()?)))
+        } else if self.accept("!") {
+            Ok(Expr::Not(Box::new(self.unary()?)))
+        } else {
+            self.primary()
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, CompileError> {
+        let line = self.line();
+        match self.next() {
+            Some(Token::Number(v)) => Ok(Expr::Number(v)),
+            Some(Token::Symbol("(")) => {
+                let expr = self.expr()?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            Some(Token::Ident(name)) if self.accept("(") => {
+                let mut args = Vec::new();
+                if !self.accept(")") {
+                    loop {
+                        args.push(self.expr()?);
+                        if self.accept(")") {
+                            break;
+                        }
+                        self.expect(",")?;
+                    }
+                }
+                match (name.as_str(), args.len()) {
+                    ("input", 0) => Ok(Expr::Input),
+                    ("output", 1) => Ok(Expr::Output(Box::new(args.remove(0)))),
+                    ("input" | "output", _) => self.error(format!("wrong number of arguments for {}", name)),
+                    _ => Ok(Expr::Call(name, args, line)),
+                }
+            }
+            Some(Token::Ident(name)) => Ok(Expr::Var(name, line)),
+            other => {
+                self.pos -= 1;
+                self.error(format!("expected an expression, found {:?}", other))
+            }
+        }
+    }
+}
+
+fn count_lets(statements: &[Stmt]) -> usize {
+    statements.iter()
+        .map(|statement| match statement {
+            Stmt::Let(..) => 1,
+            Stmt::If(_, then, otherwise) => count_lets(then) + count_lets(otherwise),
+            Stmt::While(_, body) => count_lets(body),
+            _ => 0,
+        })
+        .sum()
+}
+
+// Frame of a call, relative to the relative base on entry: the result slot pushed by the caller,
+// the arguments, the return address, then one slot per `let`, then the expression temporaries.
+// `depth` is the number of cells pushed since entry, so a slot is at rb[slot - depth].
+struct Codegen<'a> {
+    asm: String,
+    labels: usize,
+    arities: &'a HashMap<String, usize>,
+    scopes: Vec<HashMap<String, Int>>,
+    next_slot: Int,
+    result_slot: Int,
+    depth: Int,
+}
+
+impl Codegen<'_> {
+    fn emit(&mut self, line: &str) {
+        let _ = writeln!(self.asm, "    {}", line);
+    }
+
+    fn label(&mut self) -> String {
+        self.labels += 1;
+        format!(".L{}", self.labels)
+    }
+
+    fn place(&mut self, label: &str) {
+        let _ = writeln!(self.asm, "{}:", label);
+    }
+
+    fn slot(&self, name: &str, line: usize) -> Result<String, CompileError> {
+        self.scopes.iter().rev()
+            .find_map(|scope| scope.get(name))
+            .map(|slot| format!("rb[{}]", slot - self.depth))
+            .ok_or(CompileError { line, message: format!("undefined variable {}", name) })
+    }
+
+    fn push(&mut self, operand: &str) {
+        self.emit(&format!("push {}", operand));
+        self.depth += 1;
+    }
+
+    fn drop(&mut self, n: Int) {
+        self.emit(&format!("arb {}", -n));
+        self.depth -= n;
+    }
+
+    // Turns the top of the stack into 0 or 1
+    fn normalize(&mut self) {
+        self.emit("eq rb[-1], 0, rb[-1]");
+        self.emit("eq rb[-1], 0, rb[-1]");
+    }
+
+    fn function(&mut self, function: &Function) -> Result<(), CompileError> {
+        let params = function.params.len() as Int;
+        let mut scope = HashMap::new();
+        for (i, param) in function.params.iter().enumerate() {
+            scope.insert(param.clone(), i as Int - 1 - params);
+        }
+        self.scopes = vec![scope];
+        self.result_slot = -2 - params;
+        let locals = count_lets(&function.body) as Int;
+        self.next_slot = 0;
+        self.depth = locals;
+
+        self.place(&format!(".fn_{}", function.name));
+        self.emit(&format!("arb {}", locals));
+        self.block(&function.body)?;
+        self.emit(&format!("arb {}", -locals));
+        self.emit("ret");
+        Ok(())
+    }
+
+    fn block(&mut self, statements: &[Stmt]) -> Result<(), CompileError> {
+        self.scopes.push(HashMap::new());
+        for statement in statements {
+            self.statement(statement)?;
+        }
+        self.scopes.pop();
+        Ok(())
+    }
+
+    fn statement(&mut self, statement: &Stmt) -> Result<(), CompileError> {
+        match statement {
+            Stmt::Let(name, value) => {
+                self.expr(value)?;
+                let slot = self.next_slot;
+                self.next_slot += 1;
+                self.emit(&format!("pop rb[{}]", slot - self.depth + 1));
+                self.depth -= 1;
+                self.scopes.last_mut().expect("no scope").insert(name.clone(), slot);
+            }
+            Stmt::Assign(name, value, line) => {
+                self.expr(value)?;
+                self.depth -= 1;
+                let target = self.slot(name, *line)?;
+                self.emit(&format!("pop {}", target));
+            }
+            Stmt::If(condition, then, otherwise) => {
+                let (otherwise_label, end) = (self.label(), self.label());
+                self.condition(condition, &otherwise_label)?;
+                self.block(then)?;
+                self.emit(&format!("jnz 1, {}", end));
+                self.place(&otherwise_label);
+                self.block(otherwise)?;
+                self.place(&end);
+            }
+            Stmt::While(condition, body) => {
+                let (start, end) = (self.label(), self.label());
+                self.place(&start);
+                self.condition(condition, &end)?;
+                self.block(body)?;
+                self.emit(&format!("jnz 1, {}", start));
+                self.place(&end);
+            }
+            Stmt::Return(value) => {
+                if let Some(value) = value {
+                    self.expr(value)?;
+                    self.emit(&format!("pop rb[{}]", self.result_slot - self.depth + 1));
+                    self.depth -= 1;
+                }
+                self.emit(&format!("arb {}", -self.depth));
+                self.emit("ret");
+            }
+            Stmt::Expr(expr) => {
+                self.expr(expr)?;
+                self.drop(1);
+            }
+        }
+        Ok(())
+    }
+
+    // Jumps to `otherwise` when the condition is false, the value stays readable just above the stack
+    fn condition(&mut self, condition: &Expr, otherwise: &str) -> Result<(), CompileError> {
+        self.expr(condition)?;
+        self.drop(1);
+        self.emit(&format!("jz rb[0], {}", otherwise));
+        Ok(())
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Result<(), CompileError> {
+        match expr {
+            Expr::Number(v) => self.push(&v.to_string()),
+            Expr::Var(name, line) => {
+                let slot = self.slot(name, *line)?;
+                self.push(&slot);
+            }
+            Expr::Neg(value) => {
+                self.expr(value)?;
+                self.emit("mul rb[-1], -1, rb[-1]");
+            }
+            Expr::Not(value) => {
+                self.expr(value)?;
+                self.emit("eq rb[-1], 0, rb[-1]");
+            }
+            Expr::Binary(op @ ("&&" | "||"), left, right) => {
+                let end = self.label();
+                self.expr(left)?;
+                let jump = if *op == "&&" { "jz" } else { "jnz" };
+                self.emit(&format!("{} rb[-1], {}", jump, end));
+                self.drop(1);
+                self.expr(right)?;
+                self.place(&end);
+                self.normalize();
+            }
+            Expr::Binary(op, left, right) => {
+                self.expr(left)?;
+                self.expr(right)?;
+                match *op {
+                    "+" => self.emit("add rb[-2], rb[-1], rb[-2]"),
+                    "-" => {
+                        self.emit("mul rb[-1], -1, rb[-1]");
+                        self.emit("add rb[-2], rb[-1], rb[-2]");
+                    }
+                    "*" => self.emit("mul rb[-2], rb[-1], rb[-2]"),
+                    "<" => self.emit("lt rb[-2], rb[-1], rb[-2]"),
+                    ">" => self.emit("lt rb[-1], rb[-2], rb[-2]"),
+                    "<=" => {
+                        self.emit("lt rb[-1], rb[-2], rb[-2]");
+                        self.emit("eq rb[-2], 0, rb[-2]");
+                    }
+                    ">=" => {
+                        self.emit("lt rb[-2], rb[-1], rb[-2]");
+                        self.emit("eq rb[-2], 0, rb[-2]");
+                    }
+                    "==" => self.emit("eq rb[-2], rb[-1], rb[-2]"),
+                    "!=" => {
+                        self.emit("eq rb[-2], rb[-1], rb[-2]");
+                        self.emit("eq rb[-2], 0, rb[-2]");
+                    }
+                    _ => unreachable!("unknown operator {}", op),
+                }
+                self.drop(1);
+            }
+            Expr::Call(name, args, line) => {
+                match self.arities.get(name) {
+                    None => return Err(CompileError { line: *line, message: format!("undefined function {}", name) }),
+                    Some(arity) if *arity != args.len() => {
+                        let message = format!("{} takes {} arguments, got {}", name, arity, args.len());
+                        return Err(CompileError { line: *line, message });
+                    }
+                    _ => {}
+                }
+                self.push("0");
+                for arg in args {
+                    self.expr(arg)?;
+                }
+                self.emit(&format!("call .fn_{}", name));
+                self.drop(args.len() as Int);
+            }
+            Expr::Input => {
+                self.emit("in rb[0]");
+                self.emit("arb 1");
+                self.depth += 1;
+            }
+            Expr::Output(value) => {
+                self.expr(value)?;
+                self.emit("out rb[-1]");
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn compile_to_asm(source: &str) -> Result<String, CompileError> {
+    let mut parser = Parser { tokens: tokenize(source)?, pos: 0 };
+    let mut functions = Vec::new();
+    while parser.peek().is_some() {
+        functions.push(parser.function()?);
+    }
+
+    let mut arities = HashMap::new();
+    for function in &functions {
+        if arities.insert(function.name.clone(), function.params.len()).is_some() {
+            return Err(CompileError { line: 1, message: format!("function {} is defined twice", function.name) });
+        }
+    }
+    if arities.get("main") != Some(&0) {
+        return Err(CompileError { line: 1, message: String::from("missing fn main()") });
+    }
+
+    let mut codegen = Codegen {
+        asm: String::new(),
+        labels: 0,
+        arities: &arities,
+        scopes: Vec::new(),
+        next_slot: 0,
+        result_slot: 0,
+        depth: 0,
+    };
+    codegen.emit("arb __end");
+    codegen.emit("push 0");
+    codegen.emit("call .fn_main");
+    codegen.emit("halt");
+    for function in &functions {
+        codegen.function(function)?;
+    }
+    Ok(codegen.asm)
+}
+
+pub fn compile(source: &str) -> Result<Vec<Int>, CompileError> {
+    let asm = compile_to_asm(source)?;
+    let module = assemble("main", &asm).expect("Invalid generated assembly");
+    Ok(link(&[module]).expect("Invalid generated assembly").program)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::compiler::compile;
+    use crate::intcode::fuzz::{check, FuzzCase};
+    use crate::intcode::{run_with_inputs, Int};
+
+    fn run(source: &str, inputs: Vec<Int>) -> Vec<Int> {
+        run_with_inputs(compile(source).unwrap(), inputs)
+    }
+
+    #[test]
+    fn test_arithmetic_and_control_flow() {
+        let source = "
+            fn main() {
+                let a = input();
+                let b = input();
+                output(a + b * 2 - -3);
+                output(a < b && !(a == 0) || b <= 0);
+                let i = 0;
+                while i < 3 {
+                    if i == 1 { output(100); } else if i == 2 { output(200); } else { output(0); }
+                    i = i + 1;
+                }
+            }
+        ";
+        assert_eq!(run(source, vec![4, 5]), vec![17, 1, 0, 100, 200]);
+        assert_eq!(run(source, vec![0, 5]), vec![13, 0, 0, 100, 200]);
+    }
+
+    #[test]
+    fn test_recursion() {
+        let source = "
+            fn fib(n) {
+                if n < 2 { return n; }
+                return fib(n - 1) + fib(n - 2);
+            }
+
+            // Depth of the call stack grows with n
+            fn sum_to(n, acc) {
+                if n == 0 { return acc; }
+                let next = n - 1;
+                return sum_to(next, acc + n);
+            }
+
+            fn main() {
+                output(fib(input()));
+                output(sum_to(input(), 0));
+            }
+        ";
+        assert_eq!(run(source, vec![20, 1000]), vec![6765, 500500]);
+
+        let case = FuzzCase { program: compile(source).unwrap(), inputs: vec![15, 300], memory_size: 64 * 1024 };
+        assert!(check(&case, 10_000_000).is_ok());
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(compile("fn main() {\n  output(x);\n}").unwrap_err().line, 2);
+        assert!(compile("fn main() { f(1); }\nfn f(a, b) {}").is_err());
+        assert!(compile("fn f() {}").is_err());
+        assert!(compile("fn main() { let = 1; }").is_err());
+        assert!(compile("fn main() { 1 = 2; }").is_err());
+    }
+}
@@ -0,0 +1,223 @@
+use crate::intcode::binary::BinaryImage;
+use crate::intcode::cpu::{IntcodeCpu, StopReason};
+use crate::intcode::instruction::{decode_instruction, instruction_length, Instruction, Int};
+use crate::intcode::panics::catch_panic;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::ops::Range;
+
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct Branch {
+    pub taken: usize,
+    pub not_taken: usize,
+}
+
+// Hit counts per instruction address, accumulated over any number of runs
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Coverage {
+    pub runs: usize,
+    pub hits: BTreeMap<usize, usize>,
+    pub branches: BTreeMap<usize, Branch>,
+}
+
+fn decode_at(program: &[Int], addr: usize) -> Option<Instruction> {
+    let words = [0, 1, 2, 3].map(|i| program.get(addr + i).copied().unwrap_or(0));
+    instruction_length(words[0])?;
+    catch_panic(|| decode_instruction(&words)).ok()
+}
+
+impl Coverage {
+    pub fn new() -> Self {
+        Coverage::default()
+    }
+
+    // Runs the CPU for at most `max_steps` instructions, counting each one. Errors are left to the caller,
+    // the instructions executed until then are recorded.
+    pub fn record(&mut self, cpu: &mut IntcodeCpu, max_steps: usize) -> StopReason {
+        self.runs += 1;
+        for _ in 0..max_steps {
+            let pc = cpu.pc;
+            let words = [0, 1, 2, 3].map(|i| cpu.memory.peek(pc.wrapping_add(i)));
+            let steps = cpu.steps;
+            let reason = cpu.run_with_budget(1);
+            if cpu.steps == steps {
+                if reason == StopReason::Halted {
+                    *self.hits.entry(pc).or_insert(0) += 1;
+                }
+                return reason;
+            }
+            *self.hits.entry(pc).or_insert(0) += 1;
+
+            if !matches!(words[0] % 100, 5 | 6) {
+                continue;
+            }
+            // Jumps do not write memory, the condition can be read back after the step
+            let taken = match decode_instruction(&words) {
+                Instruction::JumpIfTrue { v, .. } => cpu.input_value(v) != 0,
+                Instruction::JumpIfFalse { v, .. } => cpu.input_value(v) == 0,
+                _ => continue,
+            };
+            let branch = self.branches.entry(pc).or_default();
+            if taken {
+                branch.taken += 1;
+            } else {
+                branch.not_taken += 1;
+            }
+        }
+        StopReason::OutOfBudget
+    }
+
+    pub fn merge(&mut self, other: &Coverage) {
+        self.runs += other.runs;
+        for (addr, hits) in &other.hits {
+            *self.hits.entry(*addr).or_insert(0) += hits;
+        }
+        for (addr, branch) in &other.branches {
+            let merged = self.branches.entry(*addr).or_default();
+            merged.taken += branch.taken;
+            merged.not_taken += branch.not_taken;
+        }
+    }
+
+    // Linear sweep of the program, resynchronized on every executed address. Cells that were not
+    // executed are read as instructions when they decode to one that does not overlap executed code,
+    // as data otherwise.
+    fn sweep(&self, program: &[Int]) -> Vec<(usize, Option<Instruction>)> {
+        let mut listing = Vec::new();
+        let mut addr = 0;
+        while addr < program.len() {
+            let instruction = decode_at(program, addr).filter(|instruction| {
+                let len = instruction.encode().len();
+                self.hits.contains_key(&addr)
+                    || (addr + len <= program.len()
+                        && instruction.encode() == program[addr..addr + len]
+                        && (addr + 1..addr + len).all(|cell| !self.hits.contains_key(&cell)))
+            });
+            let len = instruction.as_ref().map_or(1, |instruction| instruction.encode().len());
+            listing.push((addr, instruction));
+            addr += len;
+        }
+        listing
+    }
+
+    // Address ranges of consecutive instructions that never ran
+    pub fn never_executed(&self, program: &[Int]) -> Vec<Range<usize>> {
+        let mut blocks: Vec<Range<usize>> = Vec::new();
+        for (addr, instruction) in self.sweep(program) {
+            let instruction = match instruction {
+                Some(instruction) if !self.hits.contains_key(&addr) => instruction,
+                _ => continue,
+            };
+            let end = addr + instruction.encode().len();
+            match blocks.last_mut() {
+                Some(block) if block.end == addr => block.end = end,
+                _ => blocks.push(addr..end),
+            }
+        }
+        blocks
+    }
+
+    // The disassembly with the hit count of each instruction, `-` for the ones that never ran,
+    // and the direction taken by the conditional jumps
+    pub fn report(&self, image: &BinaryImage) -> String {
+        let listing = self.sweep(&image.program);
+        let instructions = listing.iter().filter(|(_, instruction)| instruction.is_some()).count();
+        let both_ways = self.branches.values().filter(|b| b.taken > 0 && b.not_taken > 0).count();
+        let blocks = self.never_executed(&image.program);
+
+        let mut report = String::new();
+        let _ = writeln!(
+            report,
+            "# {} runs, {} of {} instructions executed, {} of {} branches went both ways",
+            self.runs, self.hits.len(), instructions, both_ways, self.branches.len()
+        );
+        for (addr, instruction) in listing {
+            for (name, _) in image.symbols.iter().filter(|(_, start)| **start == addr) {
+                let _ = writeln!(report, "{}:", name);
+            }
+            if let Some(block) = blocks.iter().find(|block| block.start == addr) {
+                let _ = writeln!(report, "# never executed {}..{}", block.start, block.end);
+            }
+            let hits = self.hits.get(&addr).map_or(String::from("-"), |hits| hits.to_string());
+            let line = match (instruction, self.branches.get(&addr)) {
+                (Some(instruction), Some(branch)) => {
+                    let note = match (branch.taken, branch.not_taken) {
+                        (_, 0) => " (always taken)",
+                        (0, _) => " (never taken)",
+                        _ => "",
+                    };
+                    format!("{:<28} # taken {}, not taken {}{}", instruction.to_string(), branch.taken, branch.not_taken, note)
+                }
+                (Some(instruction), None) => instruction.to_string(),
+                (None, _) => format!("data {}", image.program[addr]),
+            };
+            let _ = writeln!(report, "{:>10} {:>6}  {}", hits, addr, line);
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::binary::BinaryImage;
+    use crate::intcode::coverage::{Branch, Coverage};
+    use crate::intcode::patch::PatchSet;
+    use crate::intcode::{parse_intcode_program, IntcodeCpu, StopReason};
+
+    // Outputs 0 when the input is 0, 1 otherwise
+    const IS_NONZERO: &str = "3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9";
+
+    fn run(coverage: &mut Coverage, input: i64) -> StopReason {
+        let program = parse_intcode_program(IS_NONZERO).unwrap();
+        coverage.record(&mut IntcodeCpu::new_with_inputs(program, vec![input]), 1000)
+    }
+
+    #[test]
+    fn test_branches_and_merge() {
+        let mut zero = Coverage::new();
+        assert_eq!(run(&mut zero, 0), StopReason::Halted);
+        assert_eq!(zero.branches[&2], Branch { taken: 1, not_taken: 0 });
+        assert!(!zero.hits.contains_key(&5));
+
+        let mut other = Coverage::new();
+        run(&mut other, 5);
+        zero.merge(&other);
+        assert_eq!(zero.runs, 2);
+        assert_eq!(zero.hits[&0], 2);
+        assert_eq!(zero.hits[&5], 1);
+        assert_eq!(zero.branches[&2], Branch { taken: 1, not_taken: 1 });
+
+        let report = zero.report(&BinaryImage::from_program(parse_intcode_program(IS_NONZERO).unwrap()));
+        assert!(report.contains("2      2  jz [12], [15]"));
+        assert!(report.contains("taken 1, not taken 1\n"));
+        assert!(report.contains("2     11  halt"));
+    }
+
+    #[test]
+    fn test_never_executed() {
+        let mut coverage = Coverage::new();
+        run(&mut coverage, 0);
+        let program = parse_intcode_program(IS_NONZERO).unwrap();
+
+        assert_eq!(coverage.never_executed(&program), vec![5..9]);
+        let report = coverage.report(&BinaryImage::from_program(program));
+        assert!(report.contains("# never executed 5..9\n         -      5  add [13], [14], [13]"));
+        assert!(report.contains("(always taken)"));
+    }
+
+    #[test]
+    fn test_day2_grid() {
+        let program = parse_intcode_program(include_str!("../inputs/day2.txt")).unwrap();
+        let mut coverage = Coverage::new();
+        for noun in 0..10 {
+            for verb in 0..10 {
+                let mut cpu = IntcodeCpu::new(program.clone());
+                PatchSet::new("grid").with("noun", 1, noun).with("verb", 2, verb).apply(&mut cpu).unwrap();
+                coverage.record(&mut cpu, 10_000);
+            }
+        }
+        assert_eq!(coverage.runs, 100);
+        assert!(coverage.hits.values().all(|hits| *hits == 100));
+        assert!(coverage.branches.is_empty());
+    }
+}
@@ -1,84 +1,124 @@
-use crate::intcode::instruction::{Input, Instruction, Int, decode_instruction, Output};
+use crate::intcode::instruction::{Input, Instruction, Int, decode_instruction, instruction_length, Output};
+use crate::intcode::memory::Memory;
 use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 pub struct IntcodeCpu {
-    pub memory: Vec<Int>,
+    pub memory: Memory,
     pub pc: usize,
 
     pub inputs: VecDeque<Int>,
     pub outputs: Vec<Int>,
 
     pub is_halted: bool,
-    pub relative_base: Int
+    pub relative_base: Int,
+    pub steps: usize,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum StopReason {
+    Halted,
+    WaitingForInput,
+    OutOfBudget,
 }
 
 impl IntcodeCpu {
     pub fn new_with_inputs(memory: Vec<Int>, inputs: Vec<Int>) -> Self {
-        IntcodeCpu {
-            memory,
-            pc: 0,
-            inputs: VecDeque::from(inputs),
-            outputs: Vec::new(),
-            is_halted: false,
-            relative_base: 0
-        }
+        IntcodeCpu::new_with_memory(Memory::from(memory), inputs)
     }
 
     pub fn new_with_inputs_and_large_mem(memory_size: usize, program: Vec<Int>, inputs: Vec<Int>) -> Self {
+        IntcodeCpu::new_with_memory(Memory::new(Arc::from(program), memory_size), inputs)
+    }
+
+    pub fn new_with_memory(memory: Memory, inputs: Vec<Int>) -> Self {
         IntcodeCpu {
-            memory: init_memory(memory_size, &program),
+            memory,
             pc: 0,
             inputs: VecDeque::from(inputs),
             outputs: Vec::new(),
             is_halted: false,
-            relative_base: 0
+            relative_base: 0,
+            steps: 0,
         }
     }
 
     pub fn new(memory: Vec<Int>) -> Self {
         IntcodeCpu::new_with_inputs(memory, Vec::new())
     }
+
+    pub fn fork(&self) -> Self {
+        self.clone()
+    }
 }
 
-fn init_memory(memory_size: usize, program: &Vec<Int>) -> Vec<Int> {
-    let mut memory = vec![0 as Int; memory_size];
-    memory[..program.len()].copy_from_slice(&program);
-    memory
+// CPUs are compared on the state that determines their future behaviour, the step counter is ignored
+impl PartialEq for IntcodeCpu {
+    fn eq(&self, other: &Self) -> bool {
+        self.pc == other.pc
+            && self.relative_base == other.relative_base
+            && self.is_halted == other.is_halted
+            && self.inputs == other.inputs
+            && self.outputs == other.outputs
+            && self.memory == other.memory
+    }
+}
+
+impl Eq for IntcodeCpu {}
+
+impl Hash for IntcodeCpu {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.pc.hash(state);
+        self.relative_base.hash(state);
+        self.is_halted.hash(state);
+        self.inputs.hash(state);
+        self.outputs.hash(state);
+        self.memory.hash(state);
+    }
 }
 
 impl IntcodeCpu {
-    fn input_value(&self, input: Input) -> Int {
+    pub(crate) fn input_value(&self, input: Input) -> Int {
         match input {
             Input::Position(p) => self.memory[p],
             Input::Immediate(v) => v,
-            Input::Relative(v) => self.memory[(self.relative_base + v) as usize]
+            Input::Relative(v) => self.memory[self.relative_base.wrapping_add(v) as usize]
         }
     }
 
     fn output_pos(&self, output: Output) -> usize {
         match output {
             Output::Position(p) => p,
-            Output::Relative(v) => (self.relative_base + v) as usize,
+            Output::Relative(v) => self.relative_base.wrapping_add(v) as usize,
         }
     }
 
     fn next(&mut self) -> bool {
-        let instruction = decode_instruction(&self.memory[self.pc..]);
-        self.execute(instruction)
+        let pc = self.pc;
+        // Indexed like any other access, so decoding past the end of memory panics
+        let len = instruction_length(self.memory[pc]).unwrap_or(1);
+        let words = [0, 1, 2, 3].map(|i| if i < len { self.memory[pc + i] } else { 0 });
+        let instruction = decode_instruction(&words);
+        let executed = self.execute(instruction);
+        if executed {
+            self.steps += 1;
+        }
+        executed
     }
 
     fn execute(&mut self, instruction: Instruction) -> bool {
         match instruction {
             Instruction::Add { a, b, out } => {
                 let out_pos = self.output_pos(out);
-                self.memory[out_pos] = self.input_value(a) + self.input_value(b);
+                self.memory[out_pos] = self.input_value(a).wrapping_add(self.input_value(b));
                 self.pc += 4;
                 true
             }
             Instruction::Mul { a, b, out } => {
                 let out_pos = self.output_pos(out);
-                self.memory[out_pos] = self.input_value(a) * self.input_value(b);
+                self.memory[out_pos] = self.input_value(a).wrapping_mul(self.input_value(b));
                 self.pc += 4;
                 true
             }
@@ -130,7 +170,7 @@ impl IntcodeCpu {
                 true
             },
             Instruction::RelativeBaseOffset { v } => {
-                self.relative_base += self.input_value(v);
+                self.relative_base = self.relative_base.wrapping_add(self.input_value(v));
                 self.pc += 2;
                 true
             },
@@ -144,4 +184,21 @@ impl IntcodeCpu {
     pub fn run(&mut self) {
         while self.next() {}
     }
+
+    pub fn run_with_budget(&mut self, budget: usize) -> StopReason {
+        for _ in 0..budget {
+            if !self.next() {
+                return self.stop_reason();
+            }
+        }
+        StopReason::OutOfBudget
+    }
+
+    fn stop_reason(&self) -> StopReason {
+        if self.is_halted {
+            StopReason::Halted
+        } else {
+            StopReason::WaitingForInput
+        }
+    }
 }
\ No newline at end of file
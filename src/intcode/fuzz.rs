@@ -0,0 +1,313 @@
+use crate::intcode::case::Halt;
+use crate::intcode::cpu::{IntcodeCpu, StopReason};
+use crate::intcode::instruction::{Input, Instruction, Int, Output};
+use crate::intcode::memory::Memory;
+use crate::intcode::minimize::{minimize, Minimized};
+use crate::intcode::panics::catch_panic;
+use crate::intcode::reference::{self, Execution};
+
+// Generated programs are laid out as code, then data cells, then loop counters
+const CODE_SIZE: usize = 256;
+const MAX_LOOPS: usize = 4;
+const SLACK: usize = 16;
+
+// xorshift64*, enough to generate programs reproducibly from a seed
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng((seed ^ 0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    // Uniform in [lo, hi)
+    pub fn range(&mut self, lo: Int, hi: Int) -> Int {
+        lo + (self.next_u64() % (hi - lo) as u64) as Int
+    }
+
+    pub fn chance(&mut self, percent: usize) -> bool {
+        self.below(100) < percent
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzCase {
+    pub program: Vec<Int>,
+    pub inputs: Vec<Int>,
+    pub memory_size: usize,
+}
+
+// Jumps are generated with an instruction index as target, resolved once the layout is known
+enum Item {
+    Plain(Instruction),
+    Jump { v: Input, if_true: bool, target: usize },
+}
+
+impl Item {
+    fn len(&self) -> usize {
+        match self {
+            Item::Plain(instruction) => instruction.encode().len(),
+            Item::Jump { .. } => 3,
+        }
+    }
+}
+
+pub struct ProgramGenerator {
+    rng: Rng,
+    pub max_instructions: usize,
+    pub data_size: usize,
+    pub mutation_percent: usize,
+}
+
+impl ProgramGenerator {
+    pub fn new(seed: u64) -> Self {
+        ProgramGenerator {
+            rng: Rng::new(seed),
+            max_instructions: 32,
+            data_size: 32,
+            mutation_percent: 20,
+        }
+    }
+
+    fn counter_addr(&self, n: usize) -> usize {
+        CODE_SIZE + self.data_size + n
+    }
+
+    fn memory_size(&self) -> usize {
+        CODE_SIZE + self.data_size + MAX_LOOPS + SLACK
+    }
+
+    fn input(&mut self) -> Input {
+        match self.rng.below(3) {
+            0 => Input::Position(CODE_SIZE + self.rng.below(self.data_size)),
+            1 => Input::Immediate(self.rng.range(-100, 100)),
+            _ => Input::Relative(self.rng.below(self.data_size) as Int),
+        }
+    }
+
+    fn output(&mut self) -> Output {
+        if self.rng.chance(50) {
+            Output::Position(CODE_SIZE + self.rng.below(self.data_size))
+        } else {
+            Output::Relative(self.rng.below(self.data_size) as Int)
+        }
+    }
+
+    fn item(&mut self, index: usize) -> Item {
+        let instruction = match self.rng.below(10) {
+            0 => Instruction::Add { a: self.input(), b: self.input(), out: self.output() },
+            1 => Instruction::Mul { a: self.input(), b: self.input(), out: self.output() },
+            2 => Instruction::LessThan { a: self.input(), b: self.input(), out: self.output() },
+            3 => Instruction::Equals { a: self.input(), b: self.input(), out: self.output() },
+            4 | 5 => Instruction::Out { addr: self.input() },
+            6 => Instruction::In { addr: self.output() },
+            7 => Instruction::RelativeBaseOffset { v: Input::Immediate(self.rng.range(-3, 4)) },
+            _ => {
+                let v = self.input();
+                let if_true = self.rng.chance(50);
+                return Item::Jump { v, if_true, target: index + 1 + self.rng.below(4) };
+            }
+        };
+        Item::Plain(instruction)
+    }
+
+    // Counted loop: counter = n, body, counter -= 1, jump back while counter != 0
+    fn counted_loop(&mut self, items: &mut Vec<Item>, n: usize) {
+        let counter = self.counter_addr(n);
+        let iterations = self.rng.range(1, 6);
+        items.push(Item::Plain(Instruction::Add {
+            a: Input::Immediate(iterations),
+            b: Input::Immediate(0),
+            out: Output::Position(counter),
+        }));
+        let start = items.len();
+        for _ in 0..1 + self.rng.below(3) {
+            let item = self.item(items.len());
+            items.push(item);
+        }
+        items.push(Item::Plain(Instruction::Add {
+            a: Input::Position(counter),
+            b: Input::Immediate(-1),
+            out: Output::Position(counter),
+        }));
+        items.push(Item::Jump { v: Input::Position(counter), if_true: true, target: start });
+    }
+
+    fn code(&mut self) -> Vec<Int> {
+        // The relative base starts on the data cells
+        let mut items = vec![Item::Plain(Instruction::RelativeBaseOffset { v: Input::Immediate(CODE_SIZE as Int) })];
+        let count = 1 + self.rng.below(self.max_instructions);
+        let mut loops = 0;
+        while items.len() < count {
+            if loops < MAX_LOOPS && self.rng.chance(10) {
+                self.counted_loop(&mut items, loops);
+                loops += 1;
+            } else {
+                let item = self.item(items.len());
+                items.push(item);
+            }
+        }
+        items.push(Item::Plain(Instruction::Halt));
+
+        let mut addresses = Vec::with_capacity(items.len());
+        let mut addr = 0;
+        for item in &items {
+            addresses.push(addr as Int);
+            addr += item.len();
+        }
+        assert!(addr <= CODE_SIZE, "generated code does not fit");
+
+        items.iter()
+            .flat_map(|item| match *item {
+                Item::Plain(ref instruction) => instruction.encode(),
+                Item::Jump { v, if_true, target } => {
+                    let addr = Input::Immediate(addresses[target.min(items.len() - 1)]);
+                    if if_true {
+                        Instruction::JumpIfTrue { v, addr }.encode()
+                    } else {
+                        Instruction::JumpIfFalse { v, addr }.encode()
+                    }
+                }
+            })
+            .collect()
+    }
+
+    // Near-valid programs: invalid opcodes and modes, wild addresses and values
+    fn mutate(&mut self, program: &mut [Int], code_len: usize) {
+        for _ in 0..1 + self.rng.below(3) {
+            let addr = self.rng.below(code_len);
+            program[addr] = match self.rng.below(4) {
+                0 => [0, 10, 42, 98][self.rng.below(4)],
+                1 => program[addr] % 100 + 100 * self.rng.range(0, 1000),
+                2 => self.rng.range(-4, self.memory_size() as Int + 4),
+                _ => self.rng.next_u64() as Int,
+            };
+        }
+    }
+
+    pub fn generate(&mut self) -> FuzzCase {
+        let code = self.code();
+        let mut program = code.clone();
+        program.resize(CODE_SIZE, 0);
+        program.extend((0..self.data_size).map(|_| self.rng.range(-20, 20)));
+        if self.rng.chance(self.mutation_percent) {
+            self.mutate(&mut program, code.len());
+        }
+
+        let inputs = (0..self.rng.below(5)).map(|_| self.rng.range(-10, 10)).collect();
+        FuzzCase { program, inputs, memory_size: self.memory_size() }
+    }
+}
+
+// Runs IntcodeCpu and reports its final state in the same shape as the reference interpreter
+pub fn run_cpu(program: &[Int], inputs: &[Int], memory_size: usize, max_steps: usize) -> Execution {
+    let size = memory_size.max(program.len());
+    let mut cpu = IntcodeCpu::new_with_memory(Memory::from_slice(program, size), inputs.to_vec());
+    let halt = match catch_panic(|| cpu.run_with_budget(max_steps)) {
+        Ok(reason) => Halt::Stopped(reason),
+        Err(_) => Halt::Error,
+    };
+    Execution { halt, outputs: cpu.outputs, memory: cpu.memory.to_vec(), steps: cpu.steps }
+}
+
+#[derive(Debug, Clone)]
+pub struct Divergence {
+    pub case: FuzzCase,
+    pub expected: Execution,
+    pub actual: Execution,
+}
+
+impl Divergence {
+    // Shrinks the program while the two interpreters still disagree on it
+    pub fn minimize(&self, max_steps: usize) -> Minimized {
+        minimize(&self.case.program, diverges(self.case.inputs.clone(), self.case.memory_size, max_steps))
+    }
+}
+
+pub fn diverges(inputs: Vec<Int>, memory_size: usize, max_steps: usize) -> impl FnMut(&[Int]) -> bool {
+    move |program| {
+        run_cpu(program, &inputs, memory_size, max_steps) != reference::run(program, &inputs, memory_size, max_steps)
+    }
+}
+
+pub fn check(case: &FuzzCase, max_steps: usize) -> Result<Execution, Box<Divergence>> {
+    let expected = reference::run(&case.program, &case.inputs, case.memory_size, max_steps);
+    let actual = run_cpu(&case.program, &case.inputs, case.memory_size, max_steps);
+    if actual == expected {
+        Ok(actual)
+    } else {
+        Err(Box::new(Divergence { case: case.clone(), expected, actual }))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub cases: usize,
+    pub halted: usize,
+    pub waiting_for_input: usize,
+    pub out_of_budget: usize,
+    pub errors: usize,
+}
+
+impl Summary {
+    fn record(&mut self, halt: &Halt) {
+        self.cases += 1;
+        match halt {
+            Halt::Stopped(StopReason::Halted) => self.halted += 1,
+            Halt::Stopped(StopReason::WaitingForInput) => self.waiting_for_input += 1,
+            Halt::Stopped(StopReason::OutOfBudget) => self.out_of_budget += 1,
+            Halt::Error => self.errors += 1,
+        }
+    }
+}
+
+// Compares IntcodeCpu with the reference interpreter on `iterations` generated programs,
+// stopping at the first disagreement on the outputs, the final memory, the steps or the stop reason
+pub fn fuzz(seed: u64, iterations: usize, max_steps: usize) -> Result<Summary, Box<Divergence>> {
+    let mut generator = ProgramGenerator::new(seed);
+    let mut summary = Summary::default();
+    for _ in 0..iterations {
+        let execution = check(&generator.generate(), max_steps)?;
+        summary.record(&execution.halt);
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::fuzz::{fuzz, ProgramGenerator};
+
+    #[test]
+    fn test_generator_is_deterministic() {
+        let first: Vec<_> = (0..5).map({ let mut g = ProgramGenerator::new(7); move |_| g.generate() }).collect();
+        let second: Vec<_> = (0..5).map({ let mut g = ProgramGenerator::new(7); move |_| g.generate() }).collect();
+        assert_eq!(first, second);
+        assert_ne!(ProgramGenerator::new(8).generate(), first[0]);
+    }
+
+    #[test]
+    fn test_fuzz_fixed_seed() {
+        let summary = fuzz(2019, 2000, 1000).unwrap_or_else(|divergence| {
+            let minimized = divergence.minimize(1000);
+            panic!("{:?}\nminimized: {:?}", divergence, minimized.program)
+        });
+
+        assert_eq!(summary.cases, 2000);
+        // Every way of stopping should be exercised
+        assert!(summary.halted > 0);
+        assert!(summary.waiting_for_input > 0);
+        assert!(summary.out_of_budget > 0);
+        assert!(summary.errors > 0);
+    }
+}
@@ -1,6 +1,8 @@
+use std::fmt::{Display, Formatter};
+
 pub type Int = i64;
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Instruction {
     Add {
         a: Input,
@@ -42,19 +44,106 @@ pub enum Instruction {
     Halt
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum Input {
     Position(usize),
     Immediate(Int),
     Relative(Int)
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum Output {
     Position(usize),
     Relative(Int)
 }
 
+impl Input {
+    fn encode(self) -> (Int, Int) {
+        match self {
+            Input::Position(p) => (0, p as Int),
+            Input::Immediate(v) => (1, v),
+            Input::Relative(v) => (2, v),
+        }
+    }
+}
+
+impl Output {
+    fn encode(self) -> (Int, Int) {
+        match self {
+            Output::Position(p) => (0, p as Int),
+            Output::Relative(v) => (2, v),
+        }
+    }
+}
+
+impl Instruction {
+    pub fn encode(&self) -> Vec<Int> {
+        let (opcode, params) = match *self {
+            Instruction::Add { a, b, out } => (1, vec![a.encode(), b.encode(), out.encode()]),
+            Instruction::Mul { a, b, out } => (2, vec![a.encode(), b.encode(), out.encode()]),
+            Instruction::In { addr } => (3, vec![addr.encode()]),
+            Instruction::Out { addr } => (4, vec![addr.encode()]),
+            Instruction::JumpIfTrue { v, addr } => (5, vec![v.encode(), addr.encode()]),
+            Instruction::JumpIfFalse { v, addr } => (6, vec![v.encode(), addr.encode()]),
+            Instruction::LessThan { a, b, out } => (7, vec![a.encode(), b.encode(), out.encode()]),
+            Instruction::Equals { a, b, out } => (8, vec![a.encode(), b.encode(), out.encode()]),
+            Instruction::RelativeBaseOffset { v } => (9, vec![v.encode()]),
+            Instruction::Halt => (99, vec![]),
+        };
+        let modes = params.iter().rev().fold(0, |acc, (mode, _)| acc * 10 + mode);
+        let mut code = vec![modes * 100 + opcode];
+        code.extend(params.iter().map(|(_, v)| v));
+        code
+    }
+}
+
+// Same syntax as the assembler: `[p]` position, `v` immediate, `rb[v]` relative
+impl Display for Input {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Input::Position(p) => write!(f, "[{}]", p),
+            Input::Immediate(v) => write!(f, "{}", v),
+            Input::Relative(v) => write!(f, "rb[{}]", v),
+        }
+    }
+}
+
+impl Display for Output {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Output::Position(p) => write!(f, "[{}]", p),
+            Output::Relative(v) => write!(f, "rb[{}]", v),
+        }
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Instruction::Add { a, b, out } => write!(f, "add {}, {}, {}", a, b, out),
+            Instruction::Mul { a, b, out } => write!(f, "mul {}, {}, {}", a, b, out),
+            Instruction::In { addr } => write!(f, "in {}", addr),
+            Instruction::Out { addr } => write!(f, "out {}", addr),
+            Instruction::JumpIfTrue { v, addr } => write!(f, "jnz {}, {}", v, addr),
+            Instruction::JumpIfFalse { v, addr } => write!(f, "jz {}, {}", v, addr),
+            Instruction::LessThan { a, b, out } => write!(f, "lt {}, {}, {}", a, b, out),
+            Instruction::Equals { a, b, out } => write!(f, "eq {}, {}, {}", a, b, out),
+            Instruction::RelativeBaseOffset { v } => write!(f, "arb {}", v),
+            Instruction::Halt => write!(f, "halt"),
+        }
+    }
+}
+
+pub fn instruction_length(code: Int) -> Option<usize> {
+    match code % 100 {
+        1 | 2 | 7 | 8 => Some(4),
+        3 | 4 | 9 => Some(2),
+        5 | 6 => Some(3),
+        99 => Some(1),
+        _ => None
+    }
+}
+
 pub fn decode_instruction(ptr: &[Int]) -> Instruction {
     let code = ptr[0];
     let opcode_int = code % 100;
@@ -132,7 +221,7 @@ pub fn decode_instruction(ptr: &[Int]) -> Instruction {
 
 #[cfg(test)]
 mod tests {
-    use crate::intcode::instruction::{decode_instruction, Instruction, Input, Output};
+    use crate::intcode::instruction::{decode_instruction, instruction_length, Instruction, Input, Output};
 
     #[test]
     fn test_decode() {
@@ -145,4 +234,23 @@ mod tests {
             out: Output::Position(4),
         })
     }
+
+    #[test]
+    fn test_encode() {
+        let mem = [21107, 4, -3, 7];
+        let instr = decode_instruction(&mem[0..]);
+        assert_eq!(instr.encode(), mem.to_vec());
+        assert_eq!(instr.to_string(), "lt 4, -3, rb[7]");
+        assert_eq!(Instruction::Halt.encode(), vec![99]);
+    }
+
+    #[test]
+    fn test_instruction_length() {
+        assert_eq!(instruction_length(1002), Some(4));
+        assert_eq!(instruction_length(204), Some(2));
+        assert_eq!(instruction_length(1105), Some(3));
+        assert_eq!(instruction_length(99), Some(1));
+        assert_eq!(instruction_length(42), None);
+        assert_eq!(instruction_length(-1), None);
+    }
 }
\ No newline at end of file
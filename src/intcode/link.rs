@@ -0,0 +1,95 @@
+use crate::intcode::asm::Module;
+use crate::intcode::instruction::Int;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+// First address after the linked image, free memory for a stack
+pub const END_SYMBOL: &str = "__end";
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum LinkError {
+    DuplicateSymbol(String),
+    UndefinedSymbol(String),
+}
+
+impl Display for LinkError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LinkError::DuplicateSymbol(name) => write!(f, "symbol {} is defined more than once", name),
+            LinkError::UndefinedSymbol(name) => write!(f, "undefined symbol {}", name),
+        }
+    }
+}
+
+impl Error for LinkError {}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Section {
+    pub name: String,
+    pub start: usize,
+    pub len: usize,
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Image {
+    pub program: Vec<Int>,
+    pub symbols: BTreeMap<String, usize>,
+    pub sections: Vec<Section>,
+}
+
+// Places the modules one after the other, the first one at address 0 where execution starts,
+// then resolves the references between them
+pub fn link(modules: &[Module]) -> Result<Image, LinkError> {
+    let mut image = Image::default();
+    for module in modules {
+        let start = image.program.len();
+        for (name, addr) in &module.symbols {
+            if name == END_SYMBOL || image.symbols.insert(name.clone(), start + addr).is_some() {
+                return Err(LinkError::DuplicateSymbol(name.clone()));
+            }
+        }
+        image.program.extend(&module.code);
+        for addr in &module.relocations {
+            image.program[start + addr] += start as Int;
+        }
+        image.sections.push(Section { name: module.name.clone(), start, len: module.code.len() });
+    }
+    image.symbols.insert(END_SYMBOL.to_owned(), image.program.len());
+
+    for (module, section) in modules.iter().zip(&image.sections) {
+        for (addr, name) in &module.externals {
+            let target = image.symbols.get(name).ok_or_else(|| LinkError::UndefinedSymbol(name.clone()))?;
+            image.program[section.start + addr] += *target as Int;
+        }
+    }
+    Ok(image)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::asm::assemble;
+    use crate::intcode::link::{link, LinkError, Section};
+    use crate::intcode::run_with_inputs;
+
+    #[test]
+    fn test_link() {
+        let main = assemble("main", "out [answer]\njnz 1, done").unwrap();
+        let data = assemble("data", "done: halt\n.unused: data 0\nanswer: data 42").unwrap();
+        let image = link(&[main, data]).unwrap();
+
+        assert_eq!(image.program, vec![4, 7, 1105, 1, 5, 99, 0, 42]);
+        assert_eq!(image.symbols.get("answer"), Some(&7));
+        assert_eq!(image.symbols.get("__end"), Some(&8));
+        assert_eq!(image.sections[1], Section { name: String::from("data"), start: 5, len: 3 });
+        assert_eq!(run_with_inputs(image.program, vec![]), vec![42]);
+    }
+
+    #[test]
+    fn test_link_errors() {
+        let a = assemble("a", "x: jnz 1, y").unwrap();
+        let b = assemble("b", "x: halt").unwrap();
+        assert_eq!(link(&[a.clone(), b]), Err(LinkError::DuplicateSymbol(String::from("x"))));
+        assert_eq!(link(&[a]), Err(LinkError::UndefinedSymbol(String::from("y"))));
+    }
+}
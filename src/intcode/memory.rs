@@ -0,0 +1,271 @@
+use crate::intcode::instruction::Int;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::ops::{Index, IndexMut};
+use std::sync::{Arc, OnceLock};
+
+const PAGE_BITS: usize = 8;
+pub const PAGE_SIZE: usize = 1 << PAGE_BITS;
+const PAGE_MASK: usize = PAGE_SIZE - 1;
+pub(crate) const MAX_GROWN_SIZE: usize = 1 << 28;
+
+static ZERO: Int = 0;
+
+#[derive(Debug, Clone)]
+struct Page {
+    cells: [Int; PAGE_SIZE],
+    hash: OnceLock<u64>,
+}
+
+impl Page {
+    fn zeroed() -> Self {
+        Page { cells: [0; PAGE_SIZE], hash: OnceLock::new() }
+    }
+
+    fn content_hash(&self) -> u64 {
+        *self.hash.get_or_init(|| {
+            let mut hasher = DefaultHasher::new();
+            self.cells.hash(&mut hasher);
+            hasher.finish()
+        })
+    }
+}
+
+fn zero_page_hash() -> u64 {
+    static HASH: OnceLock<u64> = OnceLock::new();
+    *HASH.get_or_init(|| Page::zeroed().content_hash())
+}
+
+// Memory made of shared pages, a page is only copied when a cell is first written.
+// Missing pages read as zeros, so large sparse memories are cheap to create and fork.
+#[derive(Debug, Clone)]
+pub struct Memory {
+    pages: Vec<Option<Arc<Page>>>,
+    size: usize,
+    // Cells loaded from the program image, the rest of the memory started zeroed
+    program_len: usize,
+    auto_grow: bool,
+}
+
+impl Memory {
+    pub fn new(image: Arc<[Int]>, size: usize) -> Self {
+        Memory::from_slice(&image, size)
+    }
+
+    pub fn from_slice(image: &[Int], size: usize) -> Self {
+        assert!(image.len() <= size, "program does not fit in {} cells", size);
+        let mut pages = vec![None; size.div_ceil(PAGE_SIZE)];
+        for (index, chunk) in image.chunks(PAGE_SIZE).enumerate() {
+            if chunk.iter().any(|c| *c != 0) {
+                let mut page = Page::zeroed();
+                page.cells[..chunk.len()].copy_from_slice(chunk);
+                pages[index] = Some(Arc::new(page));
+            }
+        }
+        Memory { pages, size, program_len: image.len(), auto_grow: false }
+    }
+
+    // Reads past the end yield zeros and writes past the end grow the memory
+    pub fn set_auto_grow(&mut self, auto_grow: bool) {
+        self.auto_grow = auto_grow;
+    }
+
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    pub fn program_len(&self) -> usize {
+        self.program_len
+    }
+
+    pub fn get(&self, addr: usize) -> Int {
+        self[addr]
+    }
+
+    pub fn set(&mut self, addr: usize, value: Int) {
+        self[addr] = value;
+    }
+
+    pub fn fork(&self) -> Self {
+        self.clone()
+    }
+
+    // Number of pages owned by this memory alone, i.e. what it has dirtied since the last fork
+    pub fn unique_pages(&self) -> usize {
+        self.pages.iter()
+            .flatten()
+            .filter(|p| Arc::strong_count(p) == 1)
+            .count()
+    }
+
+    pub(crate) fn peek(&self, addr: usize) -> Int {
+        match self.pages.get(addr >> PAGE_BITS) {
+            Some(Some(page)) => page.cells[addr & PAGE_MASK],
+            _ => 0,
+        }
+    }
+
+    pub fn to_vec(&self) -> Vec<Int> {
+        (0..self.size).map(|addr| self.peek(addr)).collect()
+    }
+
+    fn check_bounds(&self, addr: usize) {
+        assert!(addr < self.size, "address {} out of bounds (memory size {})", addr, self.size);
+    }
+
+    fn grow(&mut self, addr: usize) {
+        assert!(addr < MAX_GROWN_SIZE, "address {} out of bounds (cannot grow past {})", addr, MAX_GROWN_SIZE);
+        self.size = addr + 1;
+        self.pages.resize(self.size.div_ceil(PAGE_SIZE), None);
+    }
+
+    fn page_cells(&self, index: usize) -> &[Int; PAGE_SIZE] {
+        static ZERO_PAGE: [Int; PAGE_SIZE] = [0; PAGE_SIZE];
+        self.pages[index].as_ref().map(|p| &p.cells).unwrap_or(&ZERO_PAGE)
+    }
+}
+
+impl From<Vec<Int>> for Memory {
+    fn from(program: Vec<Int>) -> Self {
+        let size = program.len();
+        Memory::from_slice(&program, size)
+    }
+}
+
+impl Index<usize> for Memory {
+    type Output = Int;
+
+    fn index(&self, addr: usize) -> &Int {
+        if self.auto_grow && addr >= self.size {
+            return &ZERO;
+        }
+        self.check_bounds(addr);
+        match &self.pages[addr >> PAGE_BITS] {
+            Some(page) => &page.cells[addr & PAGE_MASK],
+            None => &ZERO,
+        }
+    }
+}
+
+impl IndexMut<usize> for Memory {
+    fn index_mut(&mut self, addr: usize) -> &mut Int {
+        if self.auto_grow && addr >= self.size {
+            self.grow(addr);
+        }
+        self.check_bounds(addr);
+        let page = self.pages[addr >> PAGE_BITS].get_or_insert_with(|| Arc::new(Page::zeroed()));
+        let page = Arc::make_mut(page);
+        page.hash.take();
+        &mut page.cells[addr & PAGE_MASK]
+    }
+}
+
+impl PartialEq for Memory {
+    fn eq(&self, other: &Self) -> bool {
+        self.size == other.size && self.pages.iter().zip(&other.pages)
+            .enumerate()
+            .all(|(index, pages)| match pages {
+                (Some(a), Some(b)) if Arc::ptr_eq(a, b) => true,
+                (None, None) => true,
+                _ => self.page_cells(index) == other.page_cells(index),
+            })
+    }
+}
+
+impl Eq for Memory {}
+
+impl Hash for Memory {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.size.hash(state);
+        for (index, page) in self.pages.iter().enumerate() {
+            if let Some(page) = page {
+                let page_hash = page.content_hash();
+                // Written pages holding only zeros must hash like missing ones
+                if page_hash != zero_page_hash() {
+                    index.hash(state);
+                    page_hash.hash(state);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::memory::{Memory, PAGE_SIZE};
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_fork_copies_dirty_pages_only() {
+        let mut memory = Memory::from_slice(&vec![1; 4 * PAGE_SIZE], 64 * 1024);
+        let mut fork = memory.fork();
+        assert_eq!(fork.unique_pages(), 0);
+
+        fork[10] = 7;
+        fork[11] = 8;
+        assert_eq!(fork.unique_pages(), 1);
+        assert_eq!(memory.unique_pages(), 1);
+        assert_eq!(memory[10], 1);
+        assert_eq!(fork[10], 7);
+
+        memory[60_000] = 3;
+        assert_eq!(fork[60_000], 0);
+    }
+
+    #[test]
+    fn test_eq_and_hash_ignore_history() {
+        let memory = Memory::from(vec![1, 2, 3]);
+        let mut a = memory.fork();
+        let mut b = memory.fork();
+        a[0] = 5;
+        a[0] = 1;
+        b[2] = 0;
+        b[2] = 3;
+
+        assert_eq!(a, memory);
+        assert_eq!(a, b);
+
+        let mut zeroed = Memory::from_slice(&[], 1024);
+        zeroed[1000] = 1;
+        zeroed[1000] = 0;
+        assert_eq!(zeroed, Memory::from_slice(&[], 1024));
+
+        let states: HashSet<Memory> = vec![a, b, memory, zeroed, Memory::from_slice(&[], 1024)]
+            .into_iter()
+            .collect();
+        assert_eq!(states.len(), 2);
+    }
+
+    #[test]
+    fn test_differs() {
+        let memory = Memory::from(vec![1, 2, 3]);
+        let mut fork = memory.fork();
+        fork[1] = 4;
+        assert_ne!(memory, fork);
+        assert_ne!(memory, Memory::from_slice(&[1, 2, 3], 4));
+    }
+
+    #[test]
+    fn test_auto_grow() {
+        let mut memory = Memory::from(vec![1, 2, 3]);
+        memory.set_auto_grow(true);
+        assert_eq!(memory[5000], 0);
+        assert_eq!(memory.len(), 3);
+
+        memory[5000] = 4;
+        assert_eq!(memory.len(), 5001);
+        assert_eq!(memory[5000], 4);
+        assert_eq!(memory[2], 3);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_out_of_bounds() {
+        let memory = Memory::from(vec![1, 2, 3]);
+        memory.get(3);
+    }
+}
@@ -0,0 +1,237 @@
+use crate::intcode::cpu::{IntcodeCpu, StopReason};
+use crate::intcode::instruction::{instruction_length, Int};
+use crate::intcode::memory::Memory;
+use crate::intcode::panics::catch_panic;
+use std::collections::HashSet;
+
+const HALT: Int = 99;
+const MEMORY_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Stopped { reason: StopReason, outputs: Vec<Int> },
+    Panicked(String),
+}
+
+// Runs a candidate program, interpreter panics become an outcome instead of unwinding
+pub fn run_outcome(program: &[Int], inputs: &[Int], max_steps: usize) -> Outcome {
+    let size = MEMORY_SIZE.max(program.len());
+    let mut cpu = IntcodeCpu::new_with_memory(Memory::from_slice(program, size), inputs.to_vec());
+    match catch_panic(|| cpu.run_with_budget(max_steps)) {
+        Ok(reason) => Outcome::Stopped { reason, outputs: cpu.outputs },
+        Err(message) => Outcome::Panicked(message),
+    }
+}
+
+pub fn panics(inputs: Vec<Int>, max_steps: usize) -> impl FnMut(&[Int]) -> bool {
+    move |program| matches!(run_outcome(program, &inputs, max_steps), Outcome::Panicked(_))
+}
+
+pub fn output_differs(inputs: Vec<Int>, expected: Vec<Int>, max_steps: usize) -> impl FnMut(&[Int]) -> bool {
+    move |program| match run_outcome(program, &inputs, max_steps) {
+        Outcome::Stopped { outputs, .. } => outputs != expected,
+        Outcome::Panicked(_) => false,
+    }
+}
+
+pub fn differs_from<R>(mut reference: R, inputs: Vec<Int>, max_steps: usize) -> impl FnMut(&[Int]) -> bool
+where
+    R: FnMut(&[Int], &[Int], usize) -> Outcome,
+{
+    move |program| run_outcome(program, &inputs, max_steps) != reference(program, &inputs, max_steps)
+}
+
+#[derive(Debug, Clone)]
+pub struct Minimized {
+    pub program: Vec<Int>,
+    pub tests: usize,
+}
+
+// Shrinks a program while `fails` keeps holding: truncates memory, replaces cells with halts,
+// instructions with no-ops and constants with smaller ones, until none of these helps anymore.
+pub fn minimize<F>(program: &[Int], fails: F) -> Minimized
+where
+    F: FnMut(&[Int]) -> bool,
+{
+    let mut minimizer = Minimizer { fails, tests: 0, accepted: HashSet::new() };
+    let mut program = program.to_vec();
+    if minimizer.test(&program) {
+        loop {
+            let mut progress = minimizer.truncate(&mut program);
+            progress |= minimizer.reduce(&mut program, halt_changes);
+            progress |= minimizer.reduce(&mut program, noop_changes);
+            progress |= minimizer.reduce(&mut program, |p| constant_changes(p, |_| 0));
+            progress |= minimizer.reduce(&mut program, |p| constant_changes(p, |_| 1));
+            progress |= minimizer.reduce(&mut program, |p| constant_changes(p, |v| v / 2));
+            if !progress {
+                break;
+            }
+        }
+    }
+
+    Minimized { program, tests: minimizer.tests }
+}
+
+type Change = Vec<(usize, Int)>;
+
+struct Minimizer<F> {
+    fails: F,
+    tests: usize,
+    // Passes can undo each other (a halt halved into 49 and back to 99), revisiting a program
+    // is not progress
+    accepted: HashSet<Vec<Int>>,
+}
+
+impl<F> Minimizer<F>
+where
+    F: FnMut(&[Int]) -> bool,
+{
+    fn test(&mut self, program: &[Int]) -> bool {
+        if self.accepted.contains(program) {
+            return false;
+        }
+        self.tests += 1;
+        let fails = (self.fails)(program);
+        if fails {
+            self.accepted.insert(program.to_vec());
+        }
+        fails
+    }
+
+    fn truncate(&mut self, program: &mut Vec<Int>) -> bool {
+        let initial_len = program.len();
+        let mut chunk = program.len() / 2;
+        while chunk > 0 {
+            while program.len() > chunk && self.test(&program[..program.len() - chunk]) {
+                program.truncate(program.len() - chunk);
+            }
+            chunk /= 2;
+        }
+        program.len() < initial_len
+    }
+
+    // Delta debugging over a set of independent changes, applying large groups first
+    fn reduce<C>(&mut self, program: &mut Vec<Int>, changes: C) -> bool
+    where
+        C: Fn(&[Int]) -> Vec<Change>,
+    {
+        let mut remaining = changes(program);
+        let mut progress = false;
+        let mut chunk = remaining.len();
+        while chunk > 0 {
+            let mut start = 0;
+            while start < remaining.len() {
+                let end = (start + chunk).min(remaining.len());
+                let mut candidate = program.clone();
+                for (addr, value) in remaining[start..end].iter().flatten() {
+                    candidate[*addr] = *value;
+                }
+                if self.test(&candidate) {
+                    *program = candidate;
+                    remaining.drain(start..end);
+                    progress = true;
+                } else {
+                    start = end;
+                }
+            }
+            chunk /= 2;
+        }
+        progress
+    }
+}
+
+fn halt_changes(program: &[Int]) -> Vec<Change> {
+    (0..program.len())
+        .filter(|addr| program[*addr] != HALT)
+        .map(|addr| vec![(addr, HALT)])
+        .collect()
+}
+
+// Intcode has no no-op, `109,0` (relative base += 0) and `1105,0,0` (never taken jump) act as one
+fn noop(len: usize) -> Option<&'static [Int]> {
+    match len {
+        2 => Some(&[109, 0]),
+        3 => Some(&[1105, 0, 0]),
+        4 => Some(&[109, 0, 109, 0]),
+        _ => None,
+    }
+}
+
+fn noop_changes(program: &[Int]) -> Vec<Change> {
+    let mut changes = Vec::new();
+    let mut addr = 0;
+    while addr < program.len() {
+        match instruction_length(program[addr]).filter(|len| addr + len <= program.len()) {
+            Some(len) => {
+                let current = &program[addr..addr + len];
+                if let Some(replacement) = noop(len).filter(|r| *r != current) {
+                    changes.push(replacement.iter().enumerate().map(|(i, v)| (addr + i, *v)).collect());
+                }
+                addr += len;
+            }
+            None => addr += 1,
+        }
+    }
+    changes
+}
+
+fn constant_changes<S>(program: &[Int], simplify: S) -> Vec<Change>
+where
+    S: Fn(Int) -> Int,
+{
+    program.iter()
+        .enumerate()
+        .filter_map(|(addr, v)| {
+            let simpler = simplify(*v);
+            if simpler.unsigned_abs() < v.unsigned_abs() {
+                Some(vec![(addr, simpler)])
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::minimize::{minimize, output_differs, panics, run_outcome, Outcome};
+    use crate::intcode::{parse_intcode_program, StopReason};
+
+    #[test]
+    fn test_minimize_panic() {
+        let mut program = parse_intcode_program(include_str!("../inputs/day5.txt")).unwrap();
+        // Corrupt the halt ending the first diagnostic run
+        program[222] = 42;
+        let mut fails = panics(vec![1], 10_000);
+        assert!(fails(&program));
+
+        let minimized = minimize(&program, panics(vec![1], 10_000));
+
+        assert!(fails(&minimized.program));
+        assert!(minimized.program.len() <= 4, "{:?}", minimized.program);
+    }
+
+    #[test]
+    fn test_minimize_output() {
+        let program = parse_intcode_program("3,20,1001,20,5,21,1002,21,3,22,4,22,1105,1,17,104,7,99").unwrap();
+        assert_eq!(run_outcome(&program, &[1], 100), Outcome::Stopped {
+            reason: StopReason::Halted,
+            outputs: vec![18],
+        });
+
+        let minimized = minimize(&program, |p| {
+            matches!(run_outcome(p, &[1], 100), Outcome::Stopped { outputs, .. } if outputs.contains(&18))
+        });
+
+        assert!(minimized.program.len() < program.len());
+        assert!(!output_differs(vec![1], vec![18], 100)(&minimized.program));
+    }
+
+    #[test]
+    fn test_minimize_extreme_values() {
+        // Outputs i64::MIN, which has no positive counterpart
+        let program = vec![104, i64::MIN, 99];
+        let minimized = minimize(&program, |p| p.contains(&i64::MIN));
+        assert!(minimized.program.contains(&i64::MIN) && minimized.program.len() < program.len());
+    }
+}
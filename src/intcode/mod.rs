@@ -1,17 +1,30 @@
 mod instruction;
 mod cpu;
+mod memory;
+mod parser;
+mod panics;
+mod reference;
+pub mod asm;
+pub mod batch;
+pub mod binary;
+pub mod case;
+pub mod compiler;
+pub mod coverage;
+pub mod fuzz;
+pub mod link;
+pub mod minimize;
+pub mod patch;
+pub mod protocol;
+pub mod scheduler;
+pub mod self_modify;
+pub mod session;
+pub mod stdlib;
 
-pub use cpu::IntcodeCpu;
-use instruction::{Input, Instruction, decode_instruction};
-pub use instruction::Int;
-use std::collections::VecDeque;
-
-pub fn parse_intcode_program(input: &str) -> Vec<Int> {
-    input
-        .split(',')
-        .map(|c| c.parse().expect("Unparseable int"))
-        .collect()
-}
+pub use cpu::{IntcodeCpu, StopReason};
+pub use instruction::{Int, Instruction, Input, Output, decode_instruction, instruction_length};
+pub use memory::Memory;
+pub use panics::catch_panic;
+pub use parser::{parse_intcode_program, read_intcode_program, ParseError, ParseErrorKind, ProgramReader};
 
 pub fn run_with_inputs(program: Vec<Int>, inputs: Vec<Int>) -> Vec<Int> {
     let mut cpu = IntcodeCpu::new_with_inputs_and_large_mem(64 * 1024, program, inputs);
@@ -21,45 +34,31 @@ pub fn run_with_inputs(program: Vec<Int>, inputs: Vec<Int>) -> Vec<Int> {
 
 #[cfg(test)]
 mod tests {
-    use crate::intcode::{parse_intcode_program, run_with_inputs};
+    use crate::intcode::{parse_intcode_program, IntcodeCpu};
+    use std::collections::HashSet;
 
     #[test]
-    fn test_program() {
-        let input = "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99";
-        let program = parse_intcode_program(input);
+    fn test_fork_dedup() {
+        let input = "3,9,8,9,10,9,4,9,99,-1,8";
+        let cpu = IntcodeCpu::new_with_inputs_and_large_mem(64 * 1024, parse_intcode_program(input).unwrap(), vec![]);
 
-        assert_eq!(vec![999], run_with_inputs(program.clone(), vec![4]));
-        assert_eq!(vec![1000], run_with_inputs(program.clone(), vec![8]));
-        assert_eq!(vec![1001], run_with_inputs(program.clone(), vec![10]));
-    }
+        let states: HashSet<IntcodeCpu> = [8, 7, 8, 5].iter()
+            .map(|i| {
+                let mut fork = cpu.fork();
+                fork.inputs.push_back(*i);
+                fork.run();
+                fork
+            })
+            .collect();
 
-    #[test]
-    fn test_relative_offset() {
-        let input = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
-        let program = parse_intcode_program(input);
-
-        let res = run_with_inputs(program, vec![]);
-        println!("{:?}", res);
-
-        assert_eq!(vec![109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99], res)
+        assert_eq!(states.len(), 2);
+        assert_eq!(cpu.memory[9], -1);
     }
 
     #[test]
-    fn test_bignum() {
-        let input = "1102,34915192,34915192,7,4,7,99,0";
-        let program = parse_intcode_program(input);
-
-        let res = run_with_inputs(program, vec![]);
-        println!("{:?}", res);
+    #[should_panic]
+    fn test_decode_past_end() {
+        // The add is missing its output address
+        IntcodeCpu::new(vec![1, 0, 0]).run();
     }
-
-    #[test]
-    fn test_bignum_2() {
-        let input = "104,1125899906842624,99";
-        let program = parse_intcode_program(input);
-
-        let res = run_with_inputs(program, vec![]);
-        assert_eq!(vec![1125899906842624], res);
-    }
-}
-
+}
\ No newline at end of file
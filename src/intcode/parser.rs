@@ -0,0 +1,190 @@
+use crate::intcode::instruction::Int;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io::{self, BufReader, Bytes, Read};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ParseErrorKind {
+    InvalidInt(String),
+    MissingValue,
+    Io(io::ErrorKind),
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub token_index: usize,
+    pub offset: usize,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            ParseErrorKind::InvalidInt(token) => write!(f, "invalid integer {:?}", token)?,
+            ParseErrorKind::MissingValue => write!(f, "missing value")?,
+            ParseErrorKind::Io(kind) => write!(f, "read error ({:?})", kind)?,
+        }
+        write!(f, " at token {} (byte offset {})", self.token_index, self.offset)
+    }
+}
+
+impl Error for ParseError {}
+
+// Values are separated by commas and/or whitespace, `#` starts a comment running to the end of the line.
+pub struct ProgramReader<R: Read> {
+    bytes: Bytes<BufReader<R>>,
+    offset: usize,
+    token_index: usize,
+    token: Vec<u8>,
+    token_start: usize,
+    needs_value: bool,
+    in_comment: bool,
+    done: bool,
+}
+
+impl<R: Read> ProgramReader<R> {
+    pub fn new(reader: R) -> Self {
+        ProgramReader {
+            bytes: BufReader::new(reader).bytes(),
+            offset: 0,
+            token_index: 0,
+            token: Vec::new(),
+            token_start: 0,
+            needs_value: false,
+            in_comment: false,
+            done: false,
+        }
+    }
+
+    fn error(&self, kind: ParseErrorKind, offset: usize) -> ParseError {
+        ParseError { kind, token_index: self.token_index, offset }
+    }
+
+    fn flush_token(&mut self) -> Option<Result<Int, ParseError>> {
+        if self.token.is_empty() {
+            return None;
+        }
+        let token = String::from_utf8_lossy(&self.token).into_owned();
+        self.token.clear();
+        let result = token.parse()
+            .map_err(|_| self.error(ParseErrorKind::InvalidInt(token), self.token_start));
+        self.token_index += 1;
+        self.needs_value = false;
+        Some(result)
+    }
+
+    fn process(&mut self, b: u8, offset: usize) -> Option<Result<Int, ParseError>> {
+        if self.in_comment {
+            self.in_comment = b != b'\n';
+            return None;
+        }
+        match b {
+            b'#' => {
+                self.in_comment = true;
+                self.flush_token()
+            }
+            b',' => {
+                if let Some(value) = self.flush_token() {
+                    self.needs_value = true;
+                    Some(value)
+                } else if self.needs_value || self.token_index == 0 {
+                    Some(Err(self.error(ParseErrorKind::MissingValue, offset)))
+                } else {
+                    self.needs_value = true;
+                    None
+                }
+            }
+            b if b.is_ascii_whitespace() => self.flush_token(),
+            b => {
+                if self.token.is_empty() {
+                    self.token_start = offset;
+                }
+                self.token.push(b);
+                None
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for ProgramReader<R> {
+    type Item = Result<Int, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let item = match self.bytes.next() {
+                Some(Ok(b)) => {
+                    let offset = self.offset;
+                    self.offset += 1;
+                    self.process(b, offset)
+                }
+                Some(Err(e)) => Some(Err(self.error(ParseErrorKind::Io(e.kind()), self.offset))),
+                None => {
+                    self.done = true;
+                    let needs_value = self.needs_value && self.token.is_empty();
+                    self.flush_token().or_else(|| {
+                        if needs_value {
+                            Some(Err(self.error(ParseErrorKind::MissingValue, self.offset)))
+                        } else {
+                            None
+                        }
+                    })
+                }
+            };
+            if let Some(item) = item {
+                self.done |= item.is_err();
+                return Some(item);
+            }
+        }
+        None
+    }
+}
+
+pub fn parse_intcode_program(input: &str) -> Result<Vec<Int>, ParseError> {
+    ProgramReader::new(input.as_bytes()).collect()
+}
+
+pub fn read_intcode_program<R: Read>(reader: R) -> Result<Vec<Int>, ParseError> {
+    ProgramReader::new(reader).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::parser::{parse_intcode_program, ParseError, ParseErrorKind, ProgramReader};
+
+    #[test]
+    fn test_whitespace_and_comments() {
+        let input = "# header\n1, 2 ,3,\n  4 # four\n,5\n\n";
+        assert_eq!(parse_intcode_program(input), Ok(vec![1, 2, 3, 4, 5]));
+        assert_eq!(parse_intcode_program(""), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_invalid_int() {
+        let res = parse_intcode_program("1,2,x3,4");
+        assert_eq!(res, Err(ParseError {
+            kind: ParseErrorKind::InvalidInt(String::from("x3")),
+            token_index: 2,
+            offset: 4,
+        }));
+    }
+
+    #[test]
+    fn test_missing_value() {
+        let res = parse_intcode_program("1,,2");
+        assert_eq!(res, Err(ParseError { kind: ParseErrorKind::MissingValue, token_index: 1, offset: 2 }));
+
+        let res = parse_intcode_program("1,2,\n");
+        assert_eq!(res, Err(ParseError { kind: ParseErrorKind::MissingValue, token_index: 2, offset: 5 }));
+
+        assert!(parse_intcode_program(",1").is_err());
+    }
+
+    #[test]
+    fn test_streaming() {
+        let mut reader = ProgramReader::new("104,1125899906842624,99".as_bytes());
+        assert_eq!(reader.next(), Some(Ok(104)));
+        assert_eq!(reader.next(), Some(Ok(1125899906842624)));
+        assert_eq!(reader.next(), Some(Ok(99)));
+        assert_eq!(reader.next(), None);
+    }
+}
@@ -0,0 +1,194 @@
+use crate::intcode::cpu::IntcodeCpu;
+use crate::intcode::instruction::Int;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Patch {
+    pub address: usize,
+    pub value: Int,
+    pub label: String,
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct PatchSet {
+    pub name: String,
+    pub patches: Vec<Patch>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum PatchError {
+    OutOfRange { label: String, address: usize, len: usize },
+    Conflict { label: String, address: usize },
+    Syntax { line: usize, message: String },
+    UnknownSet(String),
+}
+
+impl Display for PatchError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PatchError::OutOfRange { label, address, len } =>
+                write!(f, "patch {} at address {} is out of a program of {} cells", label, address, len),
+            PatchError::Conflict { label, address } =>
+                write!(f, "patch {} writes address {} already patched in the same set", label, address),
+            PatchError::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+            PatchError::UnknownSet(name) => write!(f, "no patch set named {}", name),
+        }
+    }
+}
+
+impl Error for PatchError {}
+
+// The values a patch set overwrote, to put them back
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Revert {
+    previous: Vec<(usize, Int)>,
+}
+
+impl Revert {
+    pub fn revert(self, cpu: &mut IntcodeCpu) {
+        for (address, value) in self.previous.into_iter().rev() {
+            cpu.memory[address] = value;
+        }
+    }
+}
+
+impl PatchSet {
+    pub fn new(name: &str) -> Self {
+        PatchSet { name: name.to_owned(), patches: Vec::new() }
+    }
+
+    pub fn with(mut self, label: &str, address: usize, value: Int) -> Self {
+        self.patches.push(Patch { address, value, label: label.to_owned() });
+        self
+    }
+
+    // Every patch must land in the program and no address may be patched twice
+    pub fn validate(&self, program_len: usize) -> Result<(), PatchError> {
+        for (i, patch) in self.patches.iter().enumerate() {
+            if patch.address >= program_len {
+                return Err(PatchError::OutOfRange {
+                    label: patch.label.clone(),
+                    address: patch.address,
+                    len: program_len,
+                });
+            }
+            if self.patches[..i].iter().any(|p| p.address == patch.address) {
+                return Err(PatchError::Conflict { label: patch.label.clone(), address: patch.address });
+            }
+        }
+        Ok(())
+    }
+
+    // Validated against the program loaded in the CPU, not the zeroed memory past it
+    pub fn apply(&self, cpu: &mut IntcodeCpu) -> Result<Revert, PatchError> {
+        self.validate(cpu.memory.program_len())?;
+        let previous = self.patches.iter()
+            .map(|patch| {
+                let previous = cpu.memory[patch.address];
+                cpu.memory[patch.address] = patch.value;
+                (patch.address, previous)
+            })
+            .collect();
+        Ok(Revert { previous })
+    }
+
+    pub fn apply_to_program(&self, program: &mut [Int]) -> Result<(), PatchError> {
+        self.validate(program.len())?;
+        for patch in &self.patches {
+            program[patch.address] = patch.value;
+        }
+        Ok(())
+    }
+}
+
+// One or more sets, each starting with a `[name]` line followed by `label: address = value` lines.
+// `#` starts a comment line:
+//
+//   [alarm_1202]
+//   noun: 1 = 12
+//   verb: 2 = 2
+pub fn parse_patch_sets(source: &str) -> Result<Vec<PatchSet>, PatchError> {
+    let mut sets: Vec<PatchSet> = Vec::new();
+    for (n, line) in source.lines().enumerate() {
+        let line = line.trim();
+        let syntax = |message: String| PatchError::Syntax { line: n + 1, message };
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            if sets.iter().any(|set| set.name == name.trim()) {
+                return Err(syntax(format!("duplicate patch set {}", name.trim())));
+            }
+            sets.push(PatchSet::new(name.trim()));
+            continue;
+        }
+
+        let (label, assignment) = line.split_once(':')
+            .ok_or_else(|| syntax(String::from("expected label: address = value")))?;
+        let (address, value) = assignment.split_once('=')
+            .ok_or_else(|| syntax(String::from("expected label: address = value")))?;
+        let address = address.trim().parse().map_err(|_| syntax(format!("invalid address {}", address.trim())))?;
+        let value = value.trim().parse().map_err(|_| syntax(format!("invalid value {}", value.trim())))?;
+        let set = sets.last_mut().ok_or_else(|| syntax(String::from("patch outside of a [set]")))?;
+        set.patches.push(Patch { address, value, label: label.trim().to_owned() });
+    }
+    Ok(sets)
+}
+
+pub fn load_patch_sets(path: &Path) -> Result<Vec<PatchSet>, String> {
+    let source = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    parse_patch_sets(&source).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+pub fn find_patch_set<'a>(sets: &'a [PatchSet], name: &str) -> Result<&'a PatchSet, PatchError> {
+    sets.iter()
+        .find(|set| set.name == name)
+        .ok_or_else(|| PatchError::UnknownSet(name.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::patch::{find_patch_set, parse_patch_sets, PatchError, PatchSet};
+    use crate::intcode::IntcodeCpu;
+
+    #[test]
+    fn test_parse() {
+        let source = "# day 2\n[alarm_1202]\nnoun: 1 = 12\nverb: 2=2\n\n[empty]\n";
+        let sets = parse_patch_sets(source).unwrap();
+
+        assert_eq!(sets.len(), 2);
+        assert_eq!(sets[0], PatchSet::new("alarm_1202").with("noun", 1, 12).with("verb", 2, 2));
+        assert!(sets[1].patches.is_empty());
+        assert_eq!(find_patch_set(&sets, "missing"), Err(PatchError::UnknownSet(String::from("missing"))));
+
+        assert!(matches!(parse_patch_sets("noun: 1 = 12"), Err(PatchError::Syntax { line: 1, .. })));
+        assert!(matches!(parse_patch_sets("[a]\nnoun: x = 12"), Err(PatchError::Syntax { line: 2, .. })));
+        assert!(matches!(parse_patch_sets("[a]\n[a]"), Err(PatchError::Syntax { line: 2, .. })));
+    }
+
+    #[test]
+    fn test_apply_and_revert() {
+        let mut cpu = IntcodeCpu::new(vec![1, 0, 0, 0, 99]);
+        let set = PatchSet::new("test").with("noun", 1, 4).with("verb", 2, 4);
+
+        let revert = set.apply(&mut cpu).unwrap();
+        assert_eq!(cpu.memory.to_vec(), vec![1, 4, 4, 0, 99]);
+        revert.revert(&mut cpu);
+        assert_eq!(cpu.memory.to_vec(), vec![1, 0, 0, 0, 99]);
+    }
+
+    #[test]
+    fn test_validate() {
+        let set = PatchSet::new("test").with("far", 10, 1);
+        assert_eq!(set.validate(5), Err(PatchError::OutOfRange { label: String::from("far"), address: 10, len: 5 }));
+        assert!(set.apply(&mut IntcodeCpu::new(vec![99])).is_err());
+        let mut cpu = IntcodeCpu::new_with_inputs_and_large_mem(1024, vec![99], vec![]);
+        assert_eq!(set.apply(&mut cpu), Err(PatchError::OutOfRange { label: String::from("far"), address: 10, len: 1 }));
+
+        let set = PatchSet::new("test").with("a", 1, 1).with("b", 1, 2);
+        assert_eq!(set.validate(5), Err(PatchError::Conflict { label: String::from("b"), address: 1 }));
+    }
+}
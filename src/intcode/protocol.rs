@@ -0,0 +1,246 @@
+use crate::intcode::cpu::IntcodeCpu;
+use crate::intcode::instruction::Int;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ProtocolError {
+    // `offset` is the index of the value in the whole output stream
+    InvalidValue { field: &'static str, value: Int, offset: usize },
+    TruncatedFrame(Vec<Int>),
+}
+
+impl Display for ProtocolError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProtocolError::InvalidValue { field, value, offset } =>
+                write!(f, "invalid {} {} at output {}", field, value, offset),
+            ProtocolError::TruncatedFrame(values) => write!(f, "output ends in a partial frame {:?}", values),
+        }
+    }
+}
+
+impl Error for ProtocolError {}
+
+// A protocol reads the output stream as consecutive frames of `frame_len` values.
+// `decode` reports invalid values with their index in the frame, the decoder turns it into a stream offset.
+pub trait OutputProtocol {
+    type Event;
+
+    fn frame_len(&self) -> usize;
+    fn decode(&self, frame: &[Int]) -> Result<Self::Event, (&'static str, usize)>;
+}
+
+#[derive(Debug, Clone)]
+pub struct Decoder<P> {
+    protocol: P,
+    pending: Vec<Int>,
+    offset: usize,
+}
+
+impl<P: OutputProtocol> Decoder<P> {
+    pub fn new(protocol: P) -> Self {
+        Decoder { protocol, pending: Vec::new(), offset: 0 }
+    }
+
+    // Decodes every complete frame, a partial frame is kept until the next call
+    pub fn feed(&mut self, values: &[Int]) -> Result<Vec<P::Event>, ProtocolError> {
+        self.pending.extend_from_slice(values);
+        let len = self.protocol.frame_len();
+        let complete = self.pending.len() - self.pending.len() % len;
+
+        let mut events = Vec::with_capacity(complete / len);
+        let mut result = Ok(());
+        let mut consumed = 0;
+        for frame in self.pending[..complete].chunks(len) {
+            consumed += len;
+            match self.protocol.decode(frame) {
+                Ok(event) => events.push(event),
+                Err((field, index)) => {
+                    let offset = self.offset + consumed - len + index;
+                    result = Err(ProtocolError::InvalidValue { field, value: frame[index], offset });
+                    break;
+                }
+            }
+        }
+        self.pending.drain(..consumed);
+        self.offset += consumed;
+        result.map(|_| events)
+    }
+
+    pub fn drain(&mut self, cpu: &mut IntcodeCpu) -> Result<Vec<P::Event>, ProtocolError> {
+        let outputs = std::mem::take(&mut cpu.outputs);
+        self.feed(&outputs)
+    }
+
+    pub fn pending(&self) -> &[Int] {
+        &self.pending
+    }
+
+    // To call once the program halted, the output must not stop in the middle of a frame
+    pub fn finish(&self) -> Result<(), ProtocolError> {
+        if self.pending.is_empty() {
+            Ok(())
+        } else {
+            Err(ProtocolError::TruncatedFrame(self.pending.clone()))
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Tile {
+    Empty,
+    Wall,
+    Block,
+    Paddle,
+    Ball,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ArcadeEvent {
+    TileDraw { x: Int, y: Int, tile: Tile },
+    Score(Int),
+}
+
+// Day 13: `x, y, tile` triples, `-1, 0, score` updates the score
+#[derive(Debug, Copy, Clone)]
+pub struct ArcadeProtocol;
+
+impl OutputProtocol for ArcadeProtocol {
+    type Event = ArcadeEvent;
+
+    fn frame_len(&self) -> usize {
+        3
+    }
+
+    fn decode(&self, frame: &[Int]) -> Result<ArcadeEvent, (&'static str, usize)> {
+        let (x, y) = (frame[0], frame[1]);
+        if x == -1 && y == 0 {
+            return Ok(ArcadeEvent::Score(frame[2]));
+        }
+        if x < 0 {
+            return Err(("x", 0));
+        }
+        if y < 0 {
+            return Err(("y", 1));
+        }
+        let tile = match frame[2] {
+            0 => Tile::Empty,
+            1 => Tile::Wall,
+            2 => Tile::Block,
+            3 => Tile::Paddle,
+            4 => Tile::Ball,
+            _ => return Err(("tile", 2)),
+        };
+        Ok(ArcadeEvent::TileDraw { x, y, tile })
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Color {
+    Black,
+    White,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Turn {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Paint {
+    pub color: Color,
+    pub turn: Turn,
+}
+
+// Day 11: `color, turn` pairs
+#[derive(Debug, Copy, Clone)]
+pub struct PaintProtocol;
+
+impl OutputProtocol for PaintProtocol {
+    type Event = Paint;
+
+    fn frame_len(&self) -> usize {
+        2
+    }
+
+    fn decode(&self, frame: &[Int]) -> Result<Paint, (&'static str, usize)> {
+        let color = match frame[0] {
+            0 => Color::Black,
+            1 => Color::White,
+            _ => return Err(("color", 0)),
+        };
+        let turn = match frame[1] {
+            0 => Turn::Left,
+            1 => Turn::Right,
+            _ => return Err(("turn", 1)),
+        };
+        Ok(Paint { color, turn })
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Status {
+    HitWall,
+    Moved,
+    FoundOxygen,
+}
+
+// Day 15: one status code per move
+#[derive(Debug, Copy, Clone)]
+pub struct StatusProtocol;
+
+impl OutputProtocol for StatusProtocol {
+    type Event = Status;
+
+    fn frame_len(&self) -> usize {
+        1
+    }
+
+    fn decode(&self, frame: &[Int]) -> Result<Status, (&'static str, usize)> {
+        match frame[0] {
+            0 => Ok(Status::HitWall),
+            1 => Ok(Status::Moved),
+            2 => Ok(Status::FoundOxygen),
+            _ => Err(("status", 0)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::protocol::{
+        ArcadeEvent, ArcadeProtocol, Color, Decoder, Paint, PaintProtocol, ProtocolError, Status, StatusProtocol,
+        Tile, Turn,
+    };
+
+    #[test]
+    fn test_partial_frames() {
+        let mut decoder = Decoder::new(ArcadeProtocol);
+        assert_eq!(decoder.feed(&[1, 2]), Ok(vec![]));
+        assert_eq!(decoder.pending(), &[1, 2]);
+        assert!(decoder.finish().is_err());
+
+        let events = decoder.feed(&[4, -1, 0, 1200, 3]).unwrap();
+        assert_eq!(events, vec![ArcadeEvent::TileDraw { x: 1, y: 2, tile: Tile::Ball }, ArcadeEvent::Score(1200)]);
+        assert_eq!(decoder.feed(&[5, 3]).unwrap(), vec![ArcadeEvent::TileDraw { x: 3, y: 5, tile: Tile::Paddle }]);
+        assert_eq!(decoder.finish(), Ok(()));
+    }
+
+    #[test]
+    fn test_malformed_output() {
+        let mut decoder = Decoder::new(ArcadeProtocol);
+        let error = decoder.feed(&[0, 0, 1, 5, 5, 7]).unwrap_err();
+        assert_eq!(error, ProtocolError::InvalidValue { field: "tile", value: 7, offset: 5 });
+
+        let mut decoder = Decoder::new(PaintProtocol);
+        assert_eq!(decoder.feed(&[1, 0]), Ok(vec![Paint { color: Color::White, turn: Turn::Left }]));
+        let error = decoder.feed(&[2, 1]).unwrap_err();
+        assert_eq!(error, ProtocolError::InvalidValue { field: "color", value: 2, offset: 2 });
+
+        let mut decoder = Decoder::new(StatusProtocol);
+        assert_eq!(decoder.feed(&[0, 2]), Ok(vec![Status::HitWall, Status::FoundOxygen]));
+        assert!(decoder.feed(&[3]).is_err());
+    }
+}
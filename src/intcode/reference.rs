@@ -0,0 +1,213 @@
+use crate::intcode::case::Halt;
+use crate::intcode::cpu::StopReason;
+use crate::intcode::instruction::Int;
+use std::collections::VecDeque;
+
+// Deliberately naive interpreter used as an oracle for IntcodeCpu: a plain vector, no decoding
+// step and no sharing. Errors are returned instead of panicking. Arithmetic wraps, an instruction
+// running past the end of memory is an error, and the budget counts attempts like run_with_budget.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Execution {
+    pub halt: Halt,
+    pub outputs: Vec<Int>,
+    pub memory: Vec<Int>,
+    pub steps: usize,
+}
+
+struct Machine {
+    memory: Vec<Int>,
+    pc: Int,
+    relative_base: Int,
+    inputs: VecDeque<Int>,
+    outputs: Vec<Int>,
+}
+
+enum Step {
+    Continue,
+    Stop(Halt),
+}
+
+impl Machine {
+    fn fetch(&self, offset: Int) -> Int {
+        let addr = self.pc.wrapping_add(offset);
+        if addr >= 0 && (addr as usize) < self.memory.len() {
+            self.memory[addr as usize]
+        } else {
+            0
+        }
+    }
+
+    // Like the decoder, every word of the instruction must be in memory, used by it or not
+    fn fits(&self, opcode: Int) -> bool {
+        let len = match opcode {
+            1 | 2 | 7 | 8 => 4,
+            3 | 4 | 9 => 2,
+            5 | 6 => 3,
+            _ => 1,
+        };
+        self.pc >= 0 && (self.pc as usize).checked_add(len).is_some_and(|end| end <= self.memory.len())
+    }
+
+    fn mode(&self, n: u32) -> Int {
+        (self.fetch(0) / 10_i64.pow(n + 2)) % 10
+    }
+
+    // Like the decoder, reject invalid modes even for parameters that end up unused
+    fn check_modes(&self, inputs: u32, outputs: u32) -> Option<()> {
+        let inputs_valid = (0..inputs).all(|n| (0..=2).contains(&self.mode(n)));
+        let outputs_valid = (inputs..inputs + outputs).all(|n| self.mode(n) == 0 || self.mode(n) == 2);
+        if inputs_valid && outputs_valid { Some(()) } else { None }
+    }
+
+    fn address(&self, n: u32) -> Option<usize> {
+        let raw = self.fetch(n as Int + 1);
+        let addr = match self.mode(n) {
+            0 => raw,
+            2 => self.relative_base.wrapping_add(raw),
+            _ => return None,
+        };
+        if addr >= 0 && (addr as usize) < self.memory.len() {
+            Some(addr as usize)
+        } else {
+            None
+        }
+    }
+
+    fn read(&self, n: u32) -> Option<Int> {
+        if self.mode(n) == 1 {
+            Some(self.fetch(n as Int + 1))
+        } else {
+            self.address(n).map(|addr| self.memory[addr])
+        }
+    }
+
+    fn write(&mut self, n: u32, value: Int) -> Option<()> {
+        let addr = self.address(n)?;
+        self.memory[addr] = value;
+        Some(())
+    }
+
+    fn step(&mut self) -> Option<Step> {
+        let opcode = self.fetch(0) % 100;
+        if !self.fits(opcode) {
+            return None;
+        }
+        match opcode {
+            1 | 2 | 7 | 8 => self.check_modes(2, 1)?,
+            3 => self.check_modes(0, 1)?,
+            4 | 9 => self.check_modes(1, 0)?,
+            5 | 6 => self.check_modes(2, 0)?,
+            _ => {}
+        }
+        match opcode {
+            1 => {
+                let v = self.read(0)?.wrapping_add(self.read(1)?);
+                self.write(2, v)?;
+                self.pc += 4;
+            }
+            2 => {
+                let v = self.read(0)?.wrapping_mul(self.read(1)?);
+                self.write(2, v)?;
+                self.pc += 4;
+            }
+            3 => {
+                match self.inputs.pop_front() {
+                    Some(v) => self.write(0, v)?,
+                    None => return Some(Step::Stop(Halt::Stopped(StopReason::WaitingForInput))),
+                }
+                self.pc += 2;
+            }
+            4 => {
+                let v = self.read(0)?;
+                self.outputs.push(v);
+                self.pc += 2;
+            }
+            5 => {
+                if self.read(0)? != 0 { self.pc = self.read(1)? } else { self.pc += 3 }
+            }
+            6 => {
+                if self.read(0)? == 0 { self.pc = self.read(1)? } else { self.pc += 3 }
+            }
+            7 => {
+                let v = (self.read(0)? < self.read(1)?) as Int;
+                self.write(2, v)?;
+                self.pc += 4;
+            }
+            8 => {
+                let v = (self.read(0)? == self.read(1)?) as Int;
+                self.write(2, v)?;
+                self.pc += 4;
+            }
+            9 => {
+                self.relative_base = self.relative_base.wrapping_add(self.read(0)?);
+                self.pc += 2;
+            }
+            99 => return Some(Step::Stop(Halt::Stopped(StopReason::Halted))),
+            _ => return None,
+        }
+        Some(Step::Continue)
+    }
+}
+
+pub fn run(program: &[Int], inputs: &[Int], memory_size: usize, max_steps: usize) -> Execution {
+    let mut memory = program.to_vec();
+    memory.resize(memory_size.max(program.len()), 0);
+    let mut machine = Machine {
+        memory,
+        pc: 0,
+        relative_base: 0,
+        inputs: inputs.iter().copied().collect(),
+        outputs: Vec::new(),
+    };
+
+    let mut steps = 0;
+    let mut halt = Halt::Stopped(StopReason::OutOfBudget);
+    for _ in 0..max_steps {
+        match machine.step() {
+            Some(Step::Continue) => steps += 1,
+            Some(Step::Stop(stop)) => {
+                halt = stop;
+                break;
+            }
+            None => {
+                halt = Halt::Error;
+                break;
+            }
+        }
+    }
+
+    Execution { halt, outputs: machine.outputs, memory: machine.memory, steps }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::case::Halt;
+    use crate::intcode::fuzz::run_cpu;
+    use crate::intcode::reference::run;
+    use crate::intcode::{parse_intcode_program, StopReason};
+
+    #[test]
+    fn test_reference() {
+        let program = parse_intcode_program("3,9,8,9,10,9,4,9,99,-1,8").unwrap();
+        let execution = run(&program, &[8], 16, 100);
+        assert_eq!(execution.halt, Halt::Stopped(StopReason::Halted));
+        assert_eq!(execution.outputs, vec![1]);
+        assert_eq!(execution.steps, 3);
+
+        let execution = run(&program, &[], 16, 100);
+        assert_eq!(execution.halt, Halt::Stopped(StopReason::WaitingForInput));
+
+        let execution = run(&[1101, 1, 1, 100, 99], &[], 16, 100);
+        assert_eq!(execution.halt, Halt::Error);
+    }
+
+    #[test]
+    fn test_instruction_past_end() {
+        // An add missing its output, then a jump to an add missing its output
+        for program in [vec![1, 0, 0], vec![1105, 1, 4, 0, 1101, 5, 5]] {
+            let execution = run(&program, &[], program.len(), 100);
+            assert_eq!(execution.halt, Halt::Error);
+            assert_eq!(execution, run_cpu(&program, &[], program.len(), 100));
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use crate::intcode::cpu::{IntcodeCpu, StopReason};
+use crate::intcode::instruction::Int;
+use std::collections::VecDeque;
+
+pub type CpuId = usize;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CpuState {
+    Ready,
+    // Parked until its input queue is fed
+    Blocked,
+    Halted,
+}
+
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct CpuStats {
+    pub steps: usize,
+    pub slices: usize,
+    pub parked: usize,
+    pub outputs: usize,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Stop {
+    AllHalted,
+    // Every CPU left is blocked on input and the idle hook fed none of them
+    Idle,
+}
+
+struct Task {
+    cpu: IntcodeCpu,
+    state: CpuState,
+    routes: Vec<CpuId>,
+    routed: usize,
+    stats: CpuStats,
+}
+
+// Runs many CPUs on the current thread, each ready CPU in turn for a slice of instructions.
+// Outputs are copied to the input queues of the connected CPUs, they stay in `cpu(id).outputs`.
+pub struct Scheduler {
+    tasks: Vec<Task>,
+    ready: VecDeque<CpuId>,
+    slice: usize,
+}
+
+impl Scheduler {
+    pub fn new(slice: usize) -> Self {
+        assert!(slice > 0, "time slice must be at least one instruction");
+        Scheduler { tasks: Vec::new(), ready: VecDeque::new(), slice }
+    }
+
+    pub fn spawn(&mut self, cpu: IntcodeCpu) -> CpuId {
+        let id = self.tasks.len();
+        self.tasks.push(Task { cpu, state: CpuState::Ready, routes: Vec::new(), routed: 0, stats: CpuStats::default() });
+        self.ready.push_back(id);
+        id
+    }
+
+    pub fn connect(&mut self, from: CpuId, to: CpuId) {
+        self.tasks[from].routes.push(to);
+    }
+
+    pub fn feed(&mut self, id: CpuId, value: Int) {
+        let task = &mut self.tasks[id];
+        task.cpu.inputs.push_back(value);
+        if task.state == CpuState::Blocked {
+            task.state = CpuState::Ready;
+            self.ready.push_back(id);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    pub fn cpu(&self, id: CpuId) -> &IntcodeCpu {
+        &self.tasks[id].cpu
+    }
+
+    pub fn state(&self, id: CpuId) -> CpuState {
+        self.tasks[id].state
+    }
+
+    pub fn stats(&self, id: CpuId) -> &CpuStats {
+        &self.tasks[id].stats
+    }
+
+    pub fn run(&mut self) -> Stop {
+        self.run_with_idle(|_| {})
+    }
+
+    // `on_idle` is called whenever no CPU is ready but some are blocked, it can feed them to go on
+    pub fn run_with_idle<F>(&mut self, mut on_idle: F) -> Stop
+    where
+        F: FnMut(&mut Scheduler),
+    {
+        loop {
+            while let Some(id) = self.ready.pop_front() {
+                self.run_slice(id);
+            }
+            if self.tasks.iter().all(|task| task.state == CpuState::Halted) {
+                return Stop::AllHalted;
+            }
+            on_idle(self);
+            if self.ready.is_empty() {
+                return Stop::Idle;
+            }
+        }
+    }
+
+    fn run_slice(&mut self, id: CpuId) {
+        let task = &mut self.tasks[id];
+        let steps_before = task.cpu.steps;
+        let reason = task.cpu.run_with_budget(self.slice);
+        task.stats.steps += task.cpu.steps - steps_before;
+        task.stats.slices += 1;
+        match reason {
+            StopReason::OutOfBudget => self.ready.push_back(id),
+            StopReason::WaitingForInput => {
+                task.state = CpuState::Blocked;
+                task.stats.parked += 1;
+            }
+            StopReason::Halted => task.state = CpuState::Halted,
+        }
+
+        let task = &mut self.tasks[id];
+        let outputs = task.cpu.outputs[task.routed..].to_vec();
+        task.routed = task.cpu.outputs.len();
+        task.stats.outputs += outputs.len();
+        for to in task.routes.clone() {
+            for v in &outputs {
+                self.feed(to, *v);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::scheduler::{CpuState, Scheduler, Stop};
+    use crate::intcode::{parse_intcode_program, IntcodeCpu};
+
+    // Outputs 1 to 50 then halts
+    const COUNTER: &str = "1001,20,1,20,4,20,1007,20,50,21,1005,21,0,99,0,0,0,0,0,0,0,0";
+    // Echoes every input multiplied by 2
+    const DOUBLER: &str = "3,11,1002,11,2,11,4,11,1105,1,0,0";
+
+    #[test]
+    fn test_round_robin() {
+        let program = parse_intcode_program(COUNTER).unwrap();
+        let mut scheduler = Scheduler::new(10);
+        let a = scheduler.spawn(IntcodeCpu::new(program.clone()));
+        let b = scheduler.spawn(IntcodeCpu::new(program));
+
+        assert_eq!(scheduler.run(), Stop::AllHalted);
+        assert_eq!(scheduler.cpu(a).outputs, (1..=50).collect::<Vec<_>>());
+        assert_eq!(scheduler.stats(a), scheduler.stats(b));
+        assert_eq!(scheduler.stats(a).steps, 200);
+        assert_eq!(scheduler.stats(a).slices, 21);
+        assert_eq!(scheduler.stats(a).outputs, 50);
+    }
+
+    #[test]
+    fn test_parking_and_idle_hook() {
+        let program = parse_intcode_program(DOUBLER).unwrap();
+        let mut scheduler = Scheduler::new(100);
+        let first = scheduler.spawn(IntcodeCpu::new(program.clone()));
+        let second = scheduler.spawn(IntcodeCpu::new(program));
+        scheduler.connect(first, second);
+
+        let mut idle_calls = 0;
+        let stop = scheduler.run_with_idle(|scheduler| {
+            idle_calls += 1;
+            if idle_calls <= 3 {
+                scheduler.feed(first, idle_calls);
+            }
+        });
+
+        assert_eq!(stop, Stop::Idle);
+        assert_eq!(idle_calls, 4);
+        assert_eq!(scheduler.cpu(second).outputs, vec![4, 8, 12]);
+        assert_eq!(scheduler.state(first), CpuState::Blocked);
+        assert_eq!(scheduler.stats(second).parked, 4);
+    }
+}
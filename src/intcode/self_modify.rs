@@ -0,0 +1,179 @@
+use crate::intcode::cpu::{IntcodeCpu, StopReason};
+use crate::intcode::instruction::{decode_instruction, instruction_length, Instruction, Int, Output};
+use crate::intcode::panics::catch_panic;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SelfModification {
+    pub step: usize,
+    pub writer_pc: usize,
+    pub target: usize,
+    pub old_value: Int,
+    pub new_value: Int,
+    // Start of the executed instruction the target belongs to
+    pub instruction_addr: usize,
+    // That instruction decoded around the write, None when the cells do not decode to a valid one
+    pub before: Option<Instruction>,
+    pub after: Option<Instruction>,
+    // Whether the instruction had already run when it was overwritten
+    pub executed_before: bool,
+}
+
+impl Display for SelfModification {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "step {}: pc {} writes {} over {} at {} (instruction at {}{}): {:?} -> {:?}",
+            self.step, self.writer_pc, self.new_value, self.old_value, self.target, self.instruction_addr,
+            if self.executed_before { ", already executed" } else { "" },
+            self.before, self.after
+        )
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Report {
+    pub modifications: Vec<SelfModification>,
+    pub stop: Option<StopReason>,
+}
+
+impl Report {
+    pub fn is_self_modifying(&self) -> bool {
+        !self.modifications.is_empty()
+    }
+}
+
+fn words(cpu: &IntcodeCpu, addr: usize) -> [Int; 4] {
+    [0, 1, 2, 3].map(|i| cpu.memory.peek(addr.wrapping_add(i)))
+}
+
+fn decode(cpu: &IntcodeCpu, addr: usize) -> Option<Instruction> {
+    let words = words(cpu, addr);
+    instruction_length(words[0])?;
+    catch_panic(|| decode_instruction(&words)).ok()
+}
+
+// Address written by the instruction at pc, if it executes
+fn write_target(cpu: &IntcodeCpu) -> Option<usize> {
+    let out = match decode(cpu, cpu.pc)? {
+        Instruction::Add { out, .. } | Instruction::Mul { out, .. } => out,
+        Instruction::LessThan { out, .. } | Instruction::Equals { out, .. } => out,
+        Instruction::In { addr } => addr,
+        _ => return None,
+    };
+    match out {
+        Output::Position(p) => Some(p),
+        Output::Relative(v) => Some(cpu.relative_base.wrapping_add(v) as usize),
+    }
+}
+
+// Runs one instruction, returns why the CPU stopped if it did. Errors are left to the caller.
+fn step(cpu: &mut IntcodeCpu) -> Option<StopReason> {
+    match cpu.run_with_budget(1) {
+        StopReason::OutOfBudget => None,
+        reason => Some(reason),
+    }
+}
+
+fn executed_cells(cpu: &IntcodeCpu) -> impl Iterator<Item = usize> {
+    let len = instruction_length(cpu.memory.peek(cpu.pc)).unwrap_or(1);
+    cpu.pc..cpu.pc + len
+}
+
+// Runs a fork of the CPU for at most `max_steps` instructions and reports every write to a cell
+// that is part of an executed instruction, before or after the write. The run is replayed twice:
+// once to find the executed cells, once to decode the instructions around each flagged write.
+pub fn analyze(cpu: &IntcodeCpu, max_steps: usize) -> Report {
+    let mut owners: HashMap<usize, usize> = HashMap::new();
+    let mut first = cpu.fork();
+    let mut stop = None;
+    for _ in 0..max_steps {
+        let pc = first.pc;
+        for cell in executed_cells(&first) {
+            owners.entry(cell).or_insert(pc);
+        }
+        stop = step(&mut first);
+        if stop.is_some() {
+            break;
+        }
+    }
+
+    let mut second = cpu.fork();
+    let mut executed = HashSet::new();
+    let mut modifications = Vec::new();
+    for _ in 0..max_steps {
+        executed.extend(executed_cells(&second));
+        let writer_pc = second.pc;
+        let target = write_target(&second).filter(|target| owners.contains_key(target));
+        let flagged = target.map(|target| {
+            let instruction_addr = owners[&target];
+            (target, instruction_addr, second.memory.peek(target), decode(&second, instruction_addr))
+        });
+        let steps = second.steps;
+        let stopped = step(&mut second).is_some();
+
+        if let Some((target, instruction_addr, old_value, before)) = flagged {
+            if second.steps > steps {
+                modifications.push(SelfModification {
+                    step: steps,
+                    writer_pc,
+                    target,
+                    old_value,
+                    new_value: second.memory[target],
+                    instruction_addr,
+                    before,
+                    after: decode(&second, instruction_addr),
+                    executed_before: executed.contains(&target),
+                });
+            }
+        }
+        if stopped {
+            break;
+        }
+    }
+
+    Report { modifications, stop }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::self_modify::analyze;
+    use crate::intcode::{parse_intcode_program, Input, Instruction, IntcodeCpu, StopReason};
+
+    #[test]
+    fn test_operand_patch() {
+        // Writes 42 into the operand of the following output instruction
+        let cpu = IntcodeCpu::new(vec![1101, 42, 0, 5, 104, 0, 99]);
+        let report = analyze(&cpu, 100);
+
+        assert_eq!(report.stop, Some(StopReason::Halted));
+        assert_eq!(report.modifications.len(), 1);
+        let modification = &report.modifications[0];
+        assert_eq!((modification.writer_pc, modification.target, modification.instruction_addr), (0, 5, 4));
+        assert_eq!(modification.before, Some(Instruction::Out { addr: Input::Immediate(0) }));
+        assert_eq!(modification.after, Some(Instruction::Out { addr: Input::Immediate(42) }));
+        assert!(!modification.executed_before);
+    }
+
+    #[test]
+    fn test_data_writes_are_not_flagged() {
+        let cpu = IntcodeCpu::new_with_inputs(parse_intcode_program("3,9,8,9,10,9,4,9,99,-1,8").unwrap(), vec![8]);
+        assert!(!analyze(&cpu, 100).is_self_modifying());
+    }
+
+    #[test]
+    fn test_day5_patches_itself() {
+        let program = parse_intcode_program(include_str!("../inputs/day5.txt")).unwrap();
+        let cpu = IntcodeCpu::new_with_inputs_and_large_mem(64 * 1024, program, vec![1]);
+        let report = analyze(&cpu, 100_000);
+
+        assert_eq!(report.stop, Some(StopReason::Halted));
+        assert!(report.is_self_modifying());
+        // The second instruction turns the invalid opcode 1100 of the third one into 1101
+        let first = &report.modifications[0];
+        assert_eq!((first.writer_pc, first.target, first.old_value, first.new_value), (2, 6, 1100, 1101));
+        assert_eq!(first.before, None);
+        assert!(first.after.is_some());
+    }
+}
@@ -0,0 +1,278 @@
+use crate::intcode::cpu::{IntcodeCpu, StopReason};
+use crate::intcode::instruction::Int;
+use crate::intcode::panics::catch_panic;
+use std::error::Error;
+use std::fmt::{Display, Formatter, Write};
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Exchange {
+    pub input: Int,
+    // Everything output after the input was read, until the next one is
+    pub outputs: Vec<Int>,
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Session {
+    // Output before the first input
+    pub initial_outputs: Vec<Int>,
+    pub exchanges: Vec<Exchange>,
+}
+
+impl Session {
+    fn batch_mut(&mut self) -> &mut Vec<Int> {
+        match self.exchanges.last_mut() {
+            Some(exchange) => &mut exchange.outputs,
+            None => &mut self.initial_outputs,
+        }
+    }
+
+    pub fn inputs(&self) -> impl Iterator<Item = Int> + '_ {
+        self.exchanges.iter().map(|exchange| exchange.input)
+    }
+
+    // `< outputs` lines and `> input` lines in the order they happened, `#` starts a comment line:
+    //
+    //   < 1,2,3
+    //   > -1
+    //   < 4
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        let join = |values: &[Int]| values.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(",");
+        if !self.initial_outputs.is_empty() {
+            let _ = writeln!(text, "< {}", join(&self.initial_outputs));
+        }
+        for exchange in &self.exchanges {
+            let _ = writeln!(text, "> {}", exchange.input);
+            if !exchange.outputs.is_empty() {
+                let _ = writeln!(text, "< {}", join(&exchange.outputs));
+            }
+        }
+        text
+    }
+
+    pub fn parse(source: &str) -> Result<Self, SessionError> {
+        let mut session = Session::default();
+        for (n, line) in source.lines().enumerate() {
+            let line = line.trim();
+            let syntax = |message: String| SessionError { line: n + 1, message };
+            let parse = |v: &str| v.trim().parse::<Int>().map_err(|_| syntax(format!("invalid value {}", v.trim())));
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(input) = line.strip_prefix('>') {
+                session.exchanges.push(Exchange { input: parse(input)?, outputs: Vec::new() });
+            } else if let Some(outputs) = line.strip_prefix('<') {
+                let values = outputs.split(',').filter(|v| !v.trim().is_empty()).map(parse).collect::<Result<Vec<_>, _>>()?;
+                session.batch_mut().extend(values);
+            } else {
+                return Err(syntax(String::from("expected > input or < outputs")));
+            }
+        }
+        Ok(session)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SessionError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl Display for SessionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for SessionError {}
+
+pub fn load_session(path: &Path) -> Result<Session, String> {
+    let source = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    Session::parse(&source).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+pub fn save_session(path: &Path, session: &Session) -> Result<(), String> {
+    fs::write(path, session.to_text()).map_err(|e| format!("cannot write {}: {}", path.display(), e))
+}
+
+// Stands in for `cpu.run()` in an interactive driver and logs the exchanges. Queued inputs are fed
+// one at a time so each output lands in the batch of the input it followed.
+#[derive(Debug, Clone, Default)]
+pub struct Recorder {
+    session: Session,
+    step_budget: Option<usize>,
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Recorder::default()
+    }
+
+    // Steps each call to `run` may execute, unbounded by default
+    pub fn step_budget(mut self, budget: usize) -> Self {
+        self.step_budget = Some(budget);
+        self
+    }
+
+    pub fn run(&mut self, cpu: &mut IntcodeCpu) -> StopReason {
+        let mut pending = std::mem::take(&mut cpu.inputs);
+        let end = cpu.steps.saturating_add(self.step_budget.unwrap_or(usize::MAX));
+        loop {
+            let start = cpu.outputs.len();
+            let reason = cpu.run_with_budget(end.saturating_sub(cpu.steps));
+            self.session.batch_mut().extend_from_slice(&cpu.outputs[start..]);
+            match pending.pop_front() {
+                Some(input) if reason == StopReason::WaitingForInput => {
+                    cpu.inputs.push_back(input);
+                    self.session.exchanges.push(Exchange { input, outputs: Vec::new() });
+                }
+                Some(input) => {
+                    // Inputs the program never read stay queued
+                    pending.push_front(input);
+                    cpu.inputs = pending;
+                    return reason;
+                }
+                None => return reason,
+            }
+        }
+    }
+
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    pub fn into_session(self) -> Session {
+        self.session
+    }
+}
+
+// `exchange` 0 is the output before the first input, `n` the one following the n-th input
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Divergence {
+    Output { exchange: usize, index: usize, expected: Option<Int>, actual: Option<Int> },
+    Halted { exchange: usize },
+    Error { exchange: usize, message: String },
+    OutOfBudget { exchange: usize },
+}
+
+impl Display for Divergence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let value = |v: &Option<Int>| v.map_or(String::from("nothing"), |v| v.to_string());
+        match self {
+            Divergence::Output { exchange, index, expected, actual } => write!(
+                f,
+                "exchange {}, output {}: expected {}, got {}",
+                exchange, index, value(expected), value(actual)
+            ),
+            Divergence::Halted { exchange } => write!(f, "program halted before reading input {}", exchange + 1),
+            Divergence::Error { exchange, message } => write!(f, "exchange {}: {}", exchange, message),
+            Divergence::OutOfBudget { exchange } => write!(f, "exchange {}: step budget exhausted", exchange),
+        }
+    }
+}
+
+impl Error for Divergence {}
+
+fn compare(exchange: usize, expected: &[Int], actual: &[Int]) -> Result<(), Box<Divergence>> {
+    let index = (0..expected.len().max(actual.len())).find(|i| expected.get(*i) != actual.get(*i));
+    match index {
+        Some(index) => Err(Box::new(Divergence::Output {
+            exchange,
+            index,
+            expected: expected.get(index).copied(),
+            actual: actual.get(index).copied(),
+        })),
+        None => Ok(()),
+    }
+}
+
+// Feeds the recorded inputs to the CPU and checks every output batch against the recording,
+// returns the CPU in its final state. `max_steps` bounds the whole replay.
+pub fn replay(mut cpu: IntcodeCpu, session: &Session, max_steps: usize) -> Result<IntcodeCpu, Box<Divergence>> {
+    let end = cpu.steps.saturating_add(max_steps);
+    let batches = std::iter::once(&session.initial_outputs).chain(session.exchanges.iter().map(|e| &e.outputs));
+    for (exchange, expected) in batches.enumerate() {
+        if exchange > 0 {
+            if cpu.is_halted {
+                return Err(Box::new(Divergence::Halted { exchange: exchange - 1 }));
+            }
+            cpu.inputs.push_back(session.exchanges[exchange - 1].input);
+        }
+        let start = cpu.outputs.len();
+        let reason = catch_panic(|| cpu.run_with_budget(end.saturating_sub(cpu.steps)))
+            .map_err(|message| Box::new(Divergence::Error { exchange, message }))?;
+        if reason == StopReason::OutOfBudget {
+            return Err(Box::new(Divergence::OutOfBudget { exchange }));
+        }
+        compare(exchange, expected, &cpu.outputs[start..])?;
+    }
+    Ok(cpu)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::session::{replay, Divergence, Exchange, Recorder, Session};
+    use crate::intcode::{IntcodeCpu, StopReason};
+
+    // Outputs 7, then for each input outputs it doubled, halts on 0
+    const DOUBLER: [i64; 18] = [104, 7, 3, 17, 1006, 17, 16, 1002, 17, 2, 17, 4, 17, 1105, 1, 2, 99, 0];
+
+    fn cpu() -> IntcodeCpu {
+        IntcodeCpu::new(DOUBLER.to_vec())
+    }
+
+    #[test]
+    fn test_record_and_replay() {
+        let mut cpu = cpu();
+        let mut recorder = Recorder::new();
+        recorder.run(&mut cpu);
+        cpu.inputs.extend(vec![3, 5]);
+        recorder.run(&mut cpu);
+        cpu.inputs.push_back(0);
+        recorder.run(&mut cpu);
+
+        let session = recorder.into_session();
+        assert_eq!(session.initial_outputs, vec![7]);
+        assert_eq!(session.exchanges, vec![
+            Exchange { input: 3, outputs: vec![6] },
+            Exchange { input: 5, outputs: vec![10] },
+            Exchange { input: 0, outputs: vec![] },
+        ]);
+        assert_eq!(Session::parse(&session.to_text()), Ok(session.clone()));
+
+        let replayed = replay(self::cpu(), &session, 1000).unwrap();
+        assert!(replayed.is_halted);
+        assert_eq!(replayed.outputs, cpu.outputs);
+    }
+
+    #[test]
+    fn test_divergence() {
+        let session = Session::parse("< 7\n> 3\n< 6\n> 5\n< 11\n").unwrap();
+        let divergence = replay(cpu(), &session, 1000).unwrap_err();
+        assert_eq!(*divergence, Divergence::Output { exchange: 2, index: 0, expected: Some(11), actual: Some(10) });
+
+        let session = Session::parse("< 7\n> 0\n> 1").unwrap();
+        assert_eq!(*replay(cpu(), &session, 1000).unwrap_err(), Divergence::Halted { exchange: 1 });
+
+        let session = Session::parse("< 7, 8\n").unwrap();
+        let divergence = replay(cpu(), &session, 1000).unwrap_err();
+        assert_eq!(*divergence, Divergence::Output { exchange: 0, index: 1, expected: Some(8), actual: None });
+
+        assert!(Session::parse("< 7\n? 1").is_err());
+    }
+
+    #[test]
+    fn test_step_budget() {
+        // Loops forever after reading its input
+        let looping = || IntcodeCpu::new(vec![3, 5, 1105, 1, 2, 0]);
+        let session = Session::parse("> 1\n").unwrap();
+        assert_eq!(*replay(looping(), &session, 1000).unwrap_err(), Divergence::OutOfBudget { exchange: 1 });
+
+        let mut cpu = looping();
+        cpu.inputs.push_back(1);
+        assert_eq!(Recorder::new().step_budget(100).run(&mut cpu), StopReason::OutOfBudget);
+        assert_eq!(cpu.steps, 100);
+    }
+}
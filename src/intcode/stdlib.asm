@@ -0,0 +1,100 @@
+# Intcode standard library
+#
+# The relative base is the stack pointer: rb[0] is the first free cell and the stack grows up,
+# a program sets it up with `arb __end` before its first call.
+# Arguments are pushed in order before `call`, the callee finds the return address at rb[-1]
+# and the last argument at rb[-2]. Results replace the arguments, the caller drops them with
+# `arb -n` or pops them. The routines use static scratch cells and are not reentrant.
+
+# print_number(n): outputs n in decimal as ASCII
+print_number:
+    add rb[-2], 0, [.pn_n]
+    lt [.pn_n], 0, [.pn_tmp]
+    jz [.pn_tmp], .pn_positive
+    out 45
+    mul [.pn_n], -1, [.pn_n]
+.pn_positive:
+    add 0, 0, [.pn_started]
+    add .pn_powers, 0, [.pn_p]
+.pn_next_power:
+    add [.pn_p], 0, [.pn_load+1]        # load the power pointed by .pn_p
+.pn_load:
+    add [0], 0, [.pn_power]
+    jz [.pn_power], .pn_done
+    add 0, 0, [.pn_digit]
+.pn_subtract:                           # no division, subtract the power until n is smaller
+    lt [.pn_n], [.pn_power], [.pn_tmp]
+    jnz [.pn_tmp], .pn_emit
+    mul [.pn_power], -1, [.pn_tmp]
+    add [.pn_n], [.pn_tmp], [.pn_n]
+    add [.pn_digit], 1, [.pn_digit]
+    jnz 1, .pn_subtract
+.pn_emit:                               # skip leading zeros, but not the last digit
+    eq [.pn_power], 1, [.pn_tmp]
+    add [.pn_tmp], [.pn_started], [.pn_tmp]
+    add [.pn_tmp], [.pn_digit], [.pn_tmp]
+    jz [.pn_tmp], .pn_skip
+    add [.pn_digit], 48, [.pn_tmp]
+    out [.pn_tmp]
+    add 1, 0, [.pn_started]
+.pn_skip:
+    add [.pn_p], 1, [.pn_p]
+    jnz 1, .pn_next_power
+.pn_done:
+    ret
+.pn_n: data 0
+.pn_tmp: data 0
+.pn_started: data 0
+.pn_p: data 0
+.pn_power: data 0
+.pn_digit: data 0
+.pn_powers:
+    data 1000000000000000000, 100000000000000000, 10000000000000000, 1000000000000000
+    data 100000000000000, 10000000000000, 1000000000000, 100000000000, 10000000000
+    data 1000000000, 100000000, 10000000, 1000000, 100000, 10000, 1000, 100, 10, 1, 0
+
+# read_line(buffer, max) -> (buffer, len): reads characters into buffer until a newline,
+# which is consumed but not stored, or until max characters were read
+read_line:
+    add rb[-3], 0, [.rl_p]
+    add 0, 0, [.rl_len]
+.rl_loop:
+    eq [.rl_len], rb[-2], [.rl_tmp]
+    jnz [.rl_tmp], .rl_done
+    in [.rl_char]
+    eq [.rl_char], 10, [.rl_tmp]
+    jnz [.rl_tmp], .rl_done
+    add [.rl_p], 0, [.rl_store+3]       # store at the address in .rl_p
+.rl_store:
+    add [.rl_char], 0, [0]
+    add [.rl_p], 1, [.rl_p]
+    add [.rl_len], 1, [.rl_len]
+    jnz 1, .rl_loop
+.rl_done:
+    add [.rl_len], 0, rb[-2]
+    ret
+.rl_p: data 0
+.rl_len: data 0
+.rl_char: data 0
+.rl_tmp: data 0
+
+# memcpy(dst, src, n): copies n cells from src to dst, front to back
+memcpy:
+    add rb[-4], 0, [.mc_dst]
+    add rb[-3], 0, [.mc_src]
+    add rb[-2], 0, [.mc_n]
+.mc_loop:
+    jz [.mc_n], .mc_done
+    add [.mc_src], 0, [.mc_copy+1]
+    add [.mc_dst], 0, [.mc_copy+3]
+.mc_copy:
+    add [0], 0, [0]
+    add [.mc_src], 1, [.mc_src]
+    add [.mc_dst], 1, [.mc_dst]
+    add [.mc_n], -1, [.mc_n]
+    jnz 1, .mc_loop
+.mc_done:
+    ret
+.mc_dst: data 0
+.mc_src: data 0
+.mc_n: data 0
@@ -0,0 +1,98 @@
+use crate::intcode::asm::{assemble, AsmError, Module};
+use crate::intcode::link::{link, Image, LinkError};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+const SOURCE: &str = include_str!("stdlib.asm");
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum BuildError {
+    Asm(AsmError),
+    Link(LinkError),
+}
+
+impl Display for BuildError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuildError::Asm(e) => write!(f, "{}", e),
+            BuildError::Link(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for BuildError {}
+
+// print_number, read_line and memcpy, see stdlib.asm for the calling convention
+pub fn stdlib() -> Module {
+    assemble("stdlib", SOURCE).expect("Invalid standard library")
+}
+
+// Assembles a program and links it before the standard library
+pub fn build(name: &str, source: &str) -> Result<Image, BuildError> {
+    let module = assemble(name, source).map_err(BuildError::Asm)?;
+    link(&[module, stdlib()]).map_err(BuildError::Link)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::stdlib::build;
+    use crate::intcode::{Int, IntcodeCpu};
+
+    fn ascii(s: &str) -> Vec<Int> {
+        s.bytes().map(Int::from).collect()
+    }
+
+    #[test]
+    fn test_print_number() {
+        let source = "
+            arb __end
+            push -1234
+            call print_number
+            arb -1
+            out 10
+            push 0
+            call print_number
+            arb -1
+            out 10
+            push 9000000000000000007
+            call print_number
+            halt
+        ";
+        let image = build("main", source).unwrap();
+        let mut cpu = IntcodeCpu::new_with_inputs_and_large_mem(4096, image.program, vec![]);
+        cpu.run();
+
+        assert!(cpu.is_halted);
+        assert_eq!(cpu.outputs, ascii("-1234\n0\n9000000000000000007"));
+    }
+
+    #[test]
+    fn test_read_line_and_memcpy() {
+        let source = "
+            arb __end
+            push buffer
+            push 16
+            call read_line
+            pop [len]
+            arb -1
+            push copy
+            push buffer
+            push [len]
+            call memcpy
+            arb -3
+            out [len]
+            halt
+            len: data 0
+            buffer: space 16
+            copy: space 16
+        ";
+        let image = build("main", source).unwrap();
+        let copy = image.symbols["copy"];
+        let mut cpu = IntcodeCpu::new_with_inputs_and_large_mem(4096, image.program, ascii("hello\nworld\n"));
+        cpu.run();
+
+        assert_eq!(cpu.outputs, vec![5]);
+        assert_eq!(cpu.memory.to_vec()[copy..copy + 6], ascii("hello\0")[..]);
+        assert_eq!(cpu.inputs.len(), 6);
+    }
+}
@@ -1,5 +1,13 @@
 pub mod utils;
+mod arena;
 pub mod intcode;
 pub mod bfs;
 pub mod vec3d;
-pub mod bfs_alt;
\ No newline at end of file
+pub mod bfs_alt;
+pub mod astar;
+pub mod bidirectional;
+pub mod dijkstra;
+pub mod heuristic;
+pub mod toposort;
+pub mod traversal;
+pub mod tree;
\ No newline at end of file
@@ -0,0 +1,198 @@
+use crate::bfs::Graph;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::hash::Hash;
+
+// Nodes of a cycle in the order of the edges, the last one has an edge back to the first
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Cycle<Node> {
+    pub nodes: Vec<Node>,
+}
+
+impl<Node: Debug> Display for Cycle<Node> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "cycle: ")?;
+        for node in &self.nodes {
+            write!(f, "{:?} -> ", node)?;
+        }
+        write!(f, "{:?}", self.nodes[0])
+    }
+}
+
+impl<Node: Debug> Error for Cycle<Node> {}
+
+type Frame<G> = (<G as Graph>::Node, <<G as Graph>::Adjacents as IntoIterator>::IntoIter);
+
+// Every node reachable from the roots, each one before all the nodes it has an edge to
+pub fn toposort<G, I>(graph: &G, roots: I) -> Result<Vec<G::Node>, Cycle<G::Node>>
+where
+    G: Graph,
+    G::Node: Eq + Hash + Clone,
+    I: IntoIterator<Item = G::Node>,
+{
+    // false while the node is on the stack, true once all its descendants are done
+    let mut done: HashMap<G::Node, bool> = HashMap::new();
+    let mut order = Vec::new();
+    let mut stack: Vec<Frame<G>> = Vec::new();
+
+    for root in roots {
+        if done.contains_key(&root) {
+            continue;
+        }
+        done.insert(root.clone(), false);
+        let adjacents = graph.adjacents(&root).into_iter();
+        stack.push((root, adjacents));
+
+        while let Some((_, adjacents)) = stack.last_mut() {
+            match adjacents.next() {
+                Some((_, next)) => match done.get(&next) {
+                    None => {
+                        done.insert(next.clone(), false);
+                        let adjacents = graph.adjacents(&next).into_iter();
+                        stack.push((next, adjacents));
+                    }
+                    Some(false) => {
+                        let start = stack.iter().position(|(node, _)| *node == next).expect("The node is on the stack");
+                        return Err(Cycle { nodes: stack.drain(start..).map(|(node, _)| node).collect() });
+                    }
+                    Some(true) => {}
+                },
+                None => {
+                    let (node, _) = stack.pop().expect("The stack is not empty");
+                    done.insert(node.clone(), true);
+                    order.push(node);
+                }
+            }
+        }
+    }
+    order.reverse();
+    Ok(order)
+}
+
+struct Tarjan<G: Graph>
+where
+    G::Node: Eq + Hash,
+{
+    // Visit order and lowest visit order reachable through the subtree, for every visited node
+    links: HashMap<G::Node, (usize, usize)>,
+    // Visited nodes not yet assigned to a component
+    open: Vec<G::Node>,
+    on_open: HashSet<G::Node>,
+    stack: Vec<Frame<G>>,
+}
+
+impl<G: Graph> Tarjan<G>
+where
+    G::Node: Eq + Hash + Clone,
+{
+    fn visit(&mut self, graph: &G, node: G::Node) {
+        let index = self.links.len();
+        self.links.insert(node.clone(), (index, index));
+        self.open.push(node.clone());
+        self.on_open.insert(node.clone());
+        let adjacents = graph.adjacents(&node).into_iter();
+        self.stack.push((node, adjacents));
+    }
+
+    fn lower(&mut self, node: &G::Node, low: usize) {
+        let link = self.links.get_mut(node).expect("The node is visited");
+        link.1 = link.1.min(low);
+    }
+}
+
+// Strongly connected components of the nodes reachable from the roots (Tarjan). A component comes
+// after every component it has an edge to, its nodes start with the first one visited.
+pub fn strongly_connected_components<G, I>(graph: &G, roots: I) -> Vec<Vec<G::Node>>
+where
+    G: Graph,
+    G::Node: Eq + Hash + Clone,
+    I: IntoIterator<Item = G::Node>,
+{
+    let mut tarjan: Tarjan<G> =
+        Tarjan { links: HashMap::new(), open: Vec::new(), on_open: HashSet::new(), stack: Vec::new() };
+    let mut components = Vec::new();
+
+    for root in roots {
+        if tarjan.links.contains_key(&root) {
+            continue;
+        }
+        tarjan.visit(graph, root);
+
+        while let Some((node, adjacents)) = tarjan.stack.last_mut() {
+            match adjacents.next() {
+                Some((_, next)) => {
+                    if !tarjan.links.contains_key(&next) {
+                        tarjan.visit(graph, next);
+                    } else if tarjan.on_open.contains(&next) {
+                        let (node, index) = (node.clone(), tarjan.links[&next].0);
+                        tarjan.lower(&node, index);
+                    }
+                }
+                None => {
+                    let (node, _) = tarjan.stack.pop().expect("The stack is not empty");
+                    let (index, low) = tarjan.links[&node];
+                    if let Some((parent, _)) = tarjan.stack.last() {
+                        let parent = parent.clone();
+                        tarjan.lower(&parent, low);
+                    }
+                    if index == low {
+                        let start = tarjan.open.iter().rposition(|n| *n == node).expect("The node is open");
+                        let component: Vec<G::Node> = tarjan.open.drain(start..).collect();
+                        for n in &component {
+                            tarjan.on_open.remove(n);
+                        }
+                        components.push(component);
+                    }
+                }
+            }
+        }
+    }
+    components
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bfs::Graph;
+    use crate::toposort::{strongly_connected_components, toposort, Cycle};
+
+    struct Edges(Vec<(u32, u32)>);
+
+    impl Graph for Edges {
+        type Node = u32;
+        type Edge = ();
+        type Adjacents = Vec<((), u32)>;
+
+        fn adjacents(&self, node: &u32) -> Vec<((), u32)> {
+            self.0.iter().filter(|(a, _)| a == node).map(|(_, b)| ((), *b)).collect()
+        }
+    }
+
+    #[test]
+    fn test_toposort() {
+        let graph = Edges(vec![(0, 1), (0, 2), (1, 3), (2, 3), (3, 4), (5, 2)]);
+        let order = toposort(&graph, vec![0, 5]).unwrap();
+        assert_eq!(order.len(), 6);
+        for (a, b) in &graph.0 {
+            let position = |n| order.iter().position(|m| *m == n).unwrap();
+            assert!(position(*a) < position(*b));
+        }
+        assert_eq!(toposort(&graph, Some(3)), Ok(vec![3, 4]));
+
+        let graph = Edges(vec![(0, 1), (1, 2), (2, 3), (3, 1)]);
+        let cycle = toposort(&graph, Some(0)).unwrap_err();
+        assert_eq!(cycle, Cycle { nodes: vec![1, 2, 3] });
+        assert_eq!(cycle.to_string(), "cycle: 1 -> 2 -> 3 -> 1");
+        assert_eq!(toposort(&Edges(vec![(7, 7)]), Some(7)).unwrap_err().nodes, vec![7]);
+    }
+
+    #[test]
+    fn test_components() {
+        let graph = Edges(vec![(0, 1), (1, 2), (2, 0), (2, 3), (3, 4), (4, 3), (5, 4)]);
+        let components = strongly_connected_components(&graph, vec![0, 5]);
+        assert_eq!(components, vec![vec![3, 4], vec![0, 1, 2], vec![5]]);
+
+        let components = strongly_connected_components(&Edges(vec![(0, 1), (1, 2)]), Some(0));
+        assert_eq!(components, vec![vec![2], vec![1], vec![0]]);
+    }
+}
@@ -0,0 +1,182 @@
+use crate::bfs::Graph;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+
+// Lazy traversals yielding `(node, depth, edge from the parent)`, the start with depth 0 and no edge.
+// Each node is yielded once. Nodes deeper than the depth limit are neither yielded nor expanded.
+pub type Step<G> = (<G as Graph>::Node, usize, Option<<G as Graph>::Edge>);
+
+pub struct Bfs<'a, G: Graph>
+where
+    G::Node: Eq + Hash,
+{
+    graph: &'a G,
+    visited: HashSet<G::Node>,
+    queue: VecDeque<Step<G>>,
+    max_depth: Option<usize>,
+}
+
+impl<'a, G: Graph> Bfs<'a, G>
+where
+    G::Node: Eq + Hash + Clone,
+{
+    pub fn new(graph: &'a G, start: G::Node) -> Self {
+        let mut visited = HashSet::new();
+        visited.insert(start.clone());
+        Bfs { graph, visited, queue: VecDeque::from(vec![(start, 0, None)]), max_depth: None }
+    }
+
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+}
+
+impl<G: Graph> Iterator for Bfs<'_, G>
+where
+    G::Node: Eq + Hash + Clone,
+{
+    type Item = Step<G>;
+
+    fn next(&mut self) -> Option<Step<G>> {
+        let (node, depth, edge) = self.queue.pop_front()?;
+        if self.max_depth.is_none_or(|max| depth < max) {
+            for (next_edge, next_node) in self.graph.adjacents(&node) {
+                if self.visited.insert(next_node.clone()) {
+                    self.queue.push_back((next_node, depth + 1, Some(next_edge)));
+                }
+            }
+        }
+        Some((node, depth, edge))
+    }
+}
+
+// Preorder depth first traversal, the depth is the one along the path that reached the node first,
+// not its distance from the start. Adjacents are visited in the order the graph returns them.
+// With a depth limit, a node reached again by a shorter path is expanded again (but not yielded),
+// so every node within the limit of the start is yielded even when the graph is not a tree.
+pub struct Dfs<'a, G: Graph>
+where
+    G::Node: Eq + Hash,
+{
+    graph: &'a G,
+    // Shallowest depth each node was expanded at
+    visited: HashMap<G::Node, usize>,
+    stack: Vec<Step<G>>,
+    max_depth: Option<usize>,
+}
+
+impl<'a, G: Graph> Dfs<'a, G>
+where
+    G::Node: Eq + Hash + Clone,
+{
+    pub fn new(graph: &'a G, start: G::Node) -> Self {
+        Dfs { graph, visited: HashMap::new(), stack: vec![(start, 0, None)], max_depth: None }
+    }
+
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    // Depths only matter with a limit, without one a node is never worth expanding twice
+    fn worth_expanding(&self, node: &G::Node, depth: usize) -> bool {
+        match self.visited.get(node) {
+            None => true,
+            Some(seen) => self.max_depth.is_some() && depth < *seen,
+        }
+    }
+}
+
+impl<G: Graph> Iterator for Dfs<'_, G>
+where
+    G::Node: Eq + Hash + Clone,
+{
+    type Item = Step<G>;
+
+    fn next(&mut self) -> Option<Step<G>> {
+        loop {
+            let (node, depth, edge) = self.stack.pop()?;
+            if !self.worth_expanding(&node, depth) {
+                continue;
+            }
+            let first = self.visited.insert(node.clone(), depth).is_none();
+            if self.max_depth.is_none_or(|max| depth < max) {
+                let start = self.stack.len();
+                for (next_edge, next_node) in self.graph.adjacents(&node) {
+                    if self.worth_expanding(&next_node, depth + 1) {
+                        self.stack.push((next_node, depth + 1, Some(next_edge)));
+                    }
+                }
+                self.stack[start..].reverse();
+            }
+            if first {
+                return Some((node, depth, edge));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bfs::Graph;
+    use crate::traversal::{Bfs, Dfs};
+
+    // Binary tree where node n has the children 2n and 2n + 1, edges are 'L' and 'R'
+    struct Binary;
+
+    impl Graph for Binary {
+        type Node = u64;
+        type Edge = char;
+        type Adjacents = [(char, u64); 2];
+
+        fn adjacents(&self, node: &u64) -> [(char, u64); 2] {
+            [('L', node * 2), ('R', node * 2 + 1)]
+        }
+    }
+
+    #[test]
+    fn test_bfs() {
+        let first: Vec<_> = Bfs::new(&Binary, 1).take(4).collect();
+        assert_eq!(first, vec![(1, 0, None), (2, 1, Some('L')), (3, 1, Some('R')), (4, 2, Some('L'))]);
+
+        // Infinite graph, only bounded by the depth limit
+        assert_eq!(Bfs::new(&Binary, 1).with_max_depth(3).count(), 15);
+        assert_eq!(Bfs::new(&Binary, 1).with_max_depth(10).filter(|(_, depth, _)| *depth == 10).count(), 1024);
+        assert_eq!(Bfs::new(&Binary, 1).find(|(node, _, _)| *node == 13).map(|(_, depth, _)| depth), Some(3));
+    }
+
+    #[test]
+    fn test_dfs() {
+        let nodes: Vec<_> = Dfs::new(&Binary, 1).with_max_depth(2).map(|(node, _, _)| node).collect();
+        assert_eq!(nodes, vec![1, 2, 4, 5, 3, 6, 7]);
+        assert_eq!(Dfs::new(&Binary, 1).nth(5), Some((32, 5, Some('L'))));
+    }
+
+    // 0 -> 1 -> 2 -> 3 and a shortcut 0 -> 2, listed after the longer path
+    struct Diamond;
+
+    impl Graph for Diamond {
+        type Node = u32;
+        type Edge = ();
+        type Adjacents = Vec<((), u32)>;
+
+        fn adjacents(&self, node: &u32) -> Vec<((), u32)> {
+            match node {
+                0 => vec![((), 1), ((), 2)],
+                1 => vec![((), 2)],
+                2 => vec![((), 3)],
+                _ => vec![],
+            }
+        }
+    }
+
+    #[test]
+    fn test_dfs_depth_limit_on_diamond() {
+        // 2 is first reached at the limit, then expanded again through the shortcut to reach 3
+        let steps: Vec<_> = Dfs::new(&Diamond, 0).with_max_depth(2).map(|(node, depth, _)| (node, depth)).collect();
+        assert_eq!(steps, vec![(0, 0), (1, 1), (2, 2), (3, 2)]);
+        assert_eq!(Dfs::new(&Diamond, 0).with_max_depth(1).count(), 3);
+        assert_eq!(Dfs::new(&Diamond, 0).count(), 4);
+    }
+}
@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::hash::Hash;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum TreeError<Node> {
+    Empty,
+    MultipleParents { node: Node, first: Node, second: Node },
+    // Every node has a parent, so they all are on cycles
+    NoRoot,
+    MultipleRoots(Vec<Node>),
+    // Not below the root, it is on a cycle or hangs from one
+    Detached(Node),
+}
+
+impl<Node: Debug> Display for TreeError<Node> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TreeError::Empty => write!(f, "the tree has no nodes"),
+            TreeError::MultipleParents { node, first, second } => {
+                write!(f, "{:?} has several parents, {:?} and {:?}", node, first, second)
+            }
+            TreeError::NoRoot => write!(f, "every node has a parent"),
+            TreeError::MultipleRoots(roots) => write!(f, "several nodes have no parent: {:?}", roots),
+            TreeError::Detached(node) => write!(f, "{:?} is not below the root", node),
+        }
+    }
+}
+
+impl<Node: Debug> Error for TreeError<Node> {}
+
+// Rooted tree with the depth of every node and binary lifting tables, `up[k][i]` is the ancestor
+// 2^k levels above node i, or the root when there are not that many levels
+#[derive(Debug, Clone)]
+pub struct Tree<Node> {
+    nodes: Vec<Node>,
+    indices: HashMap<Node, usize>,
+    root: usize,
+    depths: Vec<usize>,
+    up: Vec<Vec<usize>>,
+}
+
+impl<Node: Eq + Hash + Clone> Tree<Node> {
+    // From (parent, child) pairs
+    pub fn from_pairs<I: IntoIterator<Item = (Node, Node)>>(pairs: I) -> Result<Self, TreeError<Node>> {
+        let mut nodes: Vec<Node> = Vec::new();
+        let mut indices: HashMap<Node, usize> = HashMap::new();
+        let mut parents: Vec<Option<usize>> = Vec::new();
+        let mut index_of = |node: Node, nodes: &mut Vec<Node>, parents: &mut Vec<Option<usize>>| {
+            *indices.entry(node.clone()).or_insert_with(|| {
+                nodes.push(node);
+                parents.push(None);
+                nodes.len() - 1
+            })
+        };
+
+        for (parent, child) in pairs {
+            let parent = index_of(parent, &mut nodes, &mut parents);
+            let child = index_of(child, &mut nodes, &mut parents);
+            if let Some(first) = parents[child] {
+                return Err(TreeError::MultipleParents {
+                    node: nodes[child].clone(),
+                    first: nodes[first].clone(),
+                    second: nodes[parent].clone(),
+                });
+            }
+            parents[child] = Some(parent);
+        }
+
+        let roots: Vec<usize> = (0..nodes.len()).filter(|i| parents[*i].is_none()).collect();
+        let root = match roots.as_slice() {
+            [] if nodes.is_empty() => return Err(TreeError::Empty),
+            [] => return Err(TreeError::NoRoot),
+            [root] => *root,
+            _ => return Err(TreeError::MultipleRoots(roots.iter().map(|i| nodes[*i].clone()).collect())),
+        };
+
+        // Depths from the root down, the nodes left unset are on cycles
+        let mut children: Vec<Vec<usize>> = vec![Vec::new(); nodes.len()];
+        for (child, parent) in parents.iter().enumerate() {
+            if let Some(parent) = parent {
+                children[*parent].push(child);
+            }
+        }
+        let mut depths: Vec<Option<usize>> = vec![None; nodes.len()];
+        depths[root] = Some(0);
+        let mut stack = vec![root];
+        while let Some(node) = stack.pop() {
+            for child in &children[node] {
+                depths[*child] = depths[node].map(|d| d + 1);
+                stack.push(*child);
+            }
+        }
+        if let Some(detached) = depths.iter().position(|d| d.is_none()) {
+            return Err(TreeError::Detached(nodes[detached].clone()));
+        }
+        let depths: Vec<usize> = depths.into_iter().flatten().collect();
+
+        let max_depth = depths.iter().copied().max().unwrap_or(0);
+        let levels = (usize::BITS - max_depth.leading_zeros()).max(1) as usize;
+        let mut up = vec![parents.iter().map(|p| p.unwrap_or(root)).collect::<Vec<_>>()];
+        for k in 1..levels {
+            let next = up[k - 1].iter().map(|i| up[k - 1][*i]).collect();
+            up.push(next);
+        }
+
+        Ok(Tree { nodes, indices, root, depths, up })
+    }
+
+    pub fn root(&self) -> &Node {
+        &self.nodes[self.root]
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn contains(&self, node: &Node) -> bool {
+        self.indices.contains_key(node)
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = &Node> {
+        self.nodes.iter()
+    }
+
+    pub fn depth(&self, node: &Node) -> Option<usize> {
+        self.indices.get(node).map(|i| self.depths[*i])
+    }
+
+    pub fn parent(&self, node: &Node) -> Option<&Node> {
+        self.ancestor(node, 1)
+    }
+
+    // From the parent up to the root
+    pub fn ancestors<'a>(&'a self, node: &Node) -> impl Iterator<Item = &'a Node> {
+        let start = self.indices.get(node).copied().filter(|i| *i != self.root);
+        std::iter::successors(start.map(|i| self.up[0][i]), move |i| {
+            Some(self.up[0][*i]).filter(|_| *i != self.root)
+        })
+        .map(move |i| &self.nodes[i])
+    }
+
+    // The node `levels` above, None past the root
+    pub fn ancestor(&self, node: &Node, levels: usize) -> Option<&Node> {
+        let index = *self.indices.get(node)?;
+        if levels > self.depths[index] {
+            return None;
+        }
+        Some(&self.nodes[self.lift(index, levels)])
+    }
+
+    fn lift(&self, mut index: usize, levels: usize) -> usize {
+        for (k, up) in self.up.iter().enumerate() {
+            if levels >> k & 1 == 1 {
+                index = up[index];
+            }
+        }
+        index
+    }
+
+    fn lca_index(&self, a: usize, b: usize) -> usize {
+        let (a, b) = if self.depths[a] >= self.depths[b] { (a, b) } else { (b, a) };
+        let (mut a, mut b) = (self.lift(a, self.depths[a] - self.depths[b]), b);
+        if a == b {
+            return a;
+        }
+        for up in self.up.iter().rev() {
+            if up[a] != up[b] {
+                a = up[a];
+                b = up[b];
+            }
+        }
+        self.up[0][a]
+    }
+
+    // Lowest common ancestor, a node is an ancestor of itself
+    pub fn lca(&self, a: &Node, b: &Node) -> Option<&Node> {
+        let (a, b) = (*self.indices.get(a)?, *self.indices.get(b)?);
+        Some(&self.nodes[self.lca_index(a, b)])
+    }
+
+    // Number of edges between the nodes
+    pub fn distance(&self, a: &Node, b: &Node) -> Option<usize> {
+        let (a, b) = (*self.indices.get(a)?, *self.indices.get(b)?);
+        let lca = self.lca_index(a, b);
+        Some(self.depths[a] + self.depths[b] - 2 * self.depths[lca])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tree::{Tree, TreeError};
+
+    //       a
+    //     /   \
+    //    b     c
+    //   / \     \
+    //  d   e     f
+    //      |
+    //      g
+    fn tree() -> Tree<char> {
+        let pairs = vec![('a', 'b'), ('b', 'd'), ('e', 'g'), ('a', 'c'), ('b', 'e'), ('c', 'f')];
+        Tree::from_pairs(pairs).unwrap()
+    }
+
+    #[test]
+    fn test_queries() {
+        let tree = tree();
+        assert_eq!((tree.root(), tree.len()), (&'a', 7));
+        assert_eq!((tree.depth(&'a'), tree.depth(&'g'), tree.depth(&'z')), (Some(0), Some(3), None));
+        assert_eq!(tree.parent(&'g'), Some(&'e'));
+        assert_eq!(tree.parent(&'a'), None);
+        assert_eq!(tree.ancestors(&'g').collect::<Vec<_>>(), vec![&'e', &'b', &'a']);
+        assert_eq!(tree.ancestors(&'a').count(), 0);
+        assert_eq!((tree.ancestor(&'g', 3), tree.ancestor(&'g', 4)), (Some(&'a'), None));
+
+        assert_eq!(tree.lca(&'g', &'d'), Some(&'b'));
+        assert_eq!(tree.lca(&'g', &'f'), Some(&'a'));
+        assert_eq!(tree.lca(&'e', &'g'), Some(&'e'));
+        assert_eq!(tree.distance(&'g', &'f'), Some(5));
+        assert_eq!(tree.distance(&'d', &'d'), Some(0));
+        assert_eq!(tree.distance(&'d', &'z'), None);
+    }
+
+    #[test]
+    fn test_long_path() {
+        let tree = Tree::from_pairs((0..1000).map(|i| (i, i + 1))).unwrap();
+        assert_eq!(tree.ancestor(&1000, 999), Some(&1));
+        assert_eq!(tree.lca(&700, &300), Some(&300));
+        assert_eq!(tree.distance(&1000, &0), Some(1000));
+    }
+
+    #[test]
+    fn test_malformed() {
+        let error = Tree::from_pairs(vec![('a', 'b'), ('c', 'b')]).unwrap_err();
+        assert_eq!(error, TreeError::MultipleParents { node: 'b', first: 'a', second: 'c' });
+        assert_eq!(error.to_string(), "'b' has several parents, 'a' and 'c'");
+        assert_eq!(Tree::from_pairs(vec![('a', 'b'), ('b', 'a')]).unwrap_err(), TreeError::NoRoot);
+        assert_eq!(Tree::from_pairs(vec![('a', 'b'), ('c', 'd')]).unwrap_err(), TreeError::MultipleRoots(vec!['a', 'c']));
+        assert_eq!(Tree::from_pairs(vec![('a', 'b'), ('c', 'd'), ('d', 'c')]).unwrap_err(), TreeError::Detached('c'));
+        assert_eq!(Tree::<char>::from_pairs(vec![]).unwrap_err(), TreeError::Empty);
+    }
+}
@@ -0,0 +1,25 @@
+use std::path::Path;
+
+use aoc2019::intcode::case::run_corpus;
+
+#[test]
+fn test_intcode_corpus() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/intcode");
+    let results = run_corpus(&dir).expect("Unreadable corpus");
+    assert!(!results.is_empty(), "No case found in {}", dir.display());
+
+    for result in &results {
+        let name = result.path.file_name().unwrap().to_string_lossy();
+        if result.is_pass() {
+            println!("PASS {}", name);
+        } else {
+            println!("FAIL {}", name);
+            for failure in &result.failures {
+                println!("  {}", failure.replace('\n', "\n  "));
+            }
+        }
+    }
+
+    let failed = results.iter().filter(|r| !r.is_pass()).count();
+    assert_eq!(failed, 0, "{} of {} Intcode cases failed", failed, results.len());
+}